@@ -0,0 +1,676 @@
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use image::error::{ImageFormatHint, UnsupportedError, UnsupportedErrorKind};
+use image::{ImageBuffer, ImageError, ImageFormat, ImageResult, Rgb};
+use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
+
+use objcracker::{srgb_to_linear, Material, Objcracker, Sampler};
+
+use crate::graphics;
+use crate::graphics::*;
+use crate::light::{Light, LightSet};
+use crate::post::{PostChain, PostFrame, PostStage};
+use crate::shader::{FragmentContext, FrameUniforms, ShaderPayload};
+use crate::shadow::ShadowMap;
+use crate::tile::RenderTile;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 完成顶点阶段后等待分箱的三角形, 着色器快照中保存了该三角形的varying
+struct BinnedTri<'a> {
+    pts: Vector3<Vector4<f32>>,
+    bary: Option<ClipBary>,
+    shader: Box<dyn ShaderPayload>,
+    model: &'a Objcracker,
+    material: &'a Material,
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 一次绘制提交: 模型, 模型矩阵及其逆转置, 以及所用着色器在着色器列表中的下标
+#[derive(Clone, Copy)]
+pub struct DrawItem<'a> {
+    pub model: &'a Objcracker,
+    pub transform: Matrix4<f32>,
+    pub transform_it: Matrix4<f32>,
+    pub shader: usize,
+}
+
+impl<'a> DrawItem<'a> {
+    pub fn new(model: &'a Objcracker, transform: Matrix4<f32>, shader: usize) -> DrawItem<'a> {
+        DrawItem {
+            model,
+            transform,
+            transform_it: graphics::calc_m_inverse_transpose(&transform),
+            shader,
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// frame中为线性HDR颜色, display为后处理, 色调映射并按sRGB编码后用于显示与输出的颜色
+pub struct Delusion {
+    width: usize,
+    height: usize,
+    m_model: Matrix4<f32>,
+    m_model_it: Matrix4<f32>,
+    m_camera: Matrix4<f32>,
+    eye: Vector3<f32>,
+    m_viewport: Matrix4<f32>,
+    m_projection: Matrix4<f32>,
+    frame: RenderTile,
+    display: Vec<u32>,
+    tone: ToneOptions,
+    post: PostChain,
+    msaa_status: MsaaOptions,
+    msaa_pattern: Vec<Vector2<f32>>,
+    tile_status: Option<TileOptions>,
+    clip_planes: Vec<ClipPlane>,
+    cull_mode: CullMode,
+    front_face: FrontFace,
+    shadow_map: Option<ShadowMap>,
+    sampler: Sampler,
+}
+
+impl Delusion {
+    pub fn new(width: usize, height: usize) -> Delusion {
+        Delusion {
+            width,
+            height,
+            m_model: Matrix4::<f32>::identity(),
+            m_model_it: Matrix4::<f32>::identity(),
+            m_camera: Default::default(),
+            eye: Vector3::new(0.0, 0.0, 0.0),
+            m_viewport: Default::default(),
+            m_projection: Default::default(),
+            frame: RenderTile::new(0, 0, width, height),
+            display: vec![0; width * height],
+            tone: ToneOptions::default(),
+            post: PostChain::new(),
+            msaa_status: MsaaOptions::Disable,
+            msaa_pattern: Vec::new(),
+            tile_status: None,
+            clip_planes: calc_clip_planes(width, height, 1.0),
+            cull_mode: CullMode::None,
+            front_face: FrontFace::Ccw,
+            shadow_map: None,
+            sampler: Sampler::default(),
+        }
+    }
+
+    // 所有模型共用当前模型矩阵与同一个着色器
+    pub fn draw(
+        &mut self,
+        models: &[Objcracker],
+        shader: &mut Box<dyn ShaderPayload>,
+        lights: &[Light],
+    ) {
+        let items: Vec<DrawItem> = models
+            .iter()
+            .map(|model| DrawItem {
+                model,
+                transform: self.m_model,
+                transform_it: self.m_model_it,
+                shader: 0,
+            })
+            .collect();
+        self.draw_items(&items, std::slice::from_mut(shader), lights);
+    }
+
+    // 逐项设置模型矩阵及其逆转置, 绑定着色器uniform后执行顶点阶段
+    // 绘制结束后恢复原模型矩阵
+    // 开启阴影时先从第一个平行光的方向做深度预渲染, 主渲染的片元着色器再采样该深度图
+    pub fn draw_items(
+        &mut self,
+        items: &[DrawItem],
+        shaders: &mut [Box<dyn ShaderPayload>],
+        lights: &[Light],
+    ) {
+        let lights = Arc::new(LightSet::new(lights));
+        if let (Some(shadow_map), Some(direction)) =
+            (self.shadow_map.as_mut(), lights.shadow_direction())
+        {
+            shadow_map.render(items, &direction);
+        }
+        let (m_model, m_model_it) = (self.m_model, self.m_model_it);
+        match self.tile_status {
+            Some(options) => self.draw_tiled(items, shaders, &lights, &options),
+            None => {
+                for item in items {
+                    self.m_model = item.transform;
+                    self.m_model_it = item.transform_it;
+                    let model = item.model;
+                    let shader = &mut shaders[item.shader];
+                    shader.bind(&FrameUniforms::new(self, &lights));
+                    for i in 0..model.nfaces() {
+                        let mut screen_coords: Vector3<Vector4<f32>> = Default::default();
+                        for j in 0..3 {
+                            screen_coords[j] = shader.vertex(i, j, model, self);
+                        }
+                        self.rasterize_tri(
+                            &screen_coords,
+                            shader.as_ref(),
+                            model,
+                            model.material(i),
+                        );
+                    }
+                }
+            }
+        }
+        self.m_model = m_model;
+        self.m_model_it = m_model_it;
+    }
+
+    // 顶点阶段串行执行, 按区块分箱后多线程并行光栅化各区块
+    // 区块内三角形保持提交顺序, 输出与串行路径逐位一致
+    fn draw_tiled<'a>(
+        &mut self,
+        items: &[DrawItem<'a>],
+        shaders: &mut [Box<dyn ShaderPayload>],
+        lights: &Arc<LightSet>,
+        options: &TileOptions,
+    ) {
+        let mut tris: Vec<BinnedTri<'a>> = Vec::new();
+        for item in items {
+            self.m_model = item.transform;
+            self.m_model_it = item.transform_it;
+            let model = item.model;
+            let shader = &mut shaders[item.shader];
+            shader.bind(&FrameUniforms::new(self, lights));
+            for i in 0..model.nfaces() {
+                let mut screen_coords: Vector3<Vector4<f32>> = Default::default();
+                for j in 0..3 {
+                    screen_coords[j] = shader.vertex(i, j, model, self);
+                }
+                for clipped in clip_triangle(&screen_coords, &self.clip_planes) {
+                    if culled(&clipped.pts, self.cull_mode, self.front_face) {
+                        continue;
+                    }
+                    tris.push(BinnedTri {
+                        pts: clipped.pts,
+                        bary: clipped.bary,
+                        shader: shader.clone_box(),
+                        model,
+                        material: model.material(i),
+                    });
+                }
+            }
+        }
+
+        let size = options.tile_size;
+        let ntiles_x = self.width.div_ceil(size);
+        let ntiles_y = self.height.div_ceil(size);
+        let mut bins: Vec<Vec<usize>> = vec![Vec::new(); ntiles_x * ntiles_y];
+        for (itri, tri) in tris.iter().enumerate() {
+            let mut bboxmin: [f32; 2] = [f32::MAX, f32::MAX];
+            let mut bboxmax: [f32; 2] = [f32::MIN, f32::MIN];
+            graphics::sample_bounding_box(&tri.pts, &self.msaa_pattern, &mut bboxmin, &mut bboxmax);
+            let xmin = bboxmin[0].ceil().max(0.0) as usize;
+            let xmax = bboxmax[0].ceil().min(self.width as f32) as usize;
+            let ymin = bboxmin[1].ceil().max(0.0) as usize;
+            let ymax = bboxmax[1].ceil().min(self.height as f32) as usize;
+            if xmin >= xmax || ymin >= ymax {
+                continue;
+            }
+            for ty in ymin / size..=(ymax - 1) / size {
+                for tx in xmin / size..=(xmax - 1) / size {
+                    bins[tx + ty * ntiles_x].push(itri);
+                }
+            }
+        }
+
+        let mut tiles: Vec<Mutex<RenderTile>> = Vec::with_capacity(bins.len());
+        for ty in 0..ntiles_y {
+            for tx in 0..ntiles_x {
+                let (x0, y0) = (tx * size, ty * size);
+                let w = size.min(self.width - x0);
+                let h = size.min(self.height - y0);
+                tiles.push(Mutex::new(RenderTile::split_from(
+                    &self.frame,
+                    x0,
+                    y0,
+                    w,
+                    h,
+                )));
+            }
+        }
+
+        let next = AtomicUsize::new(0);
+        let pattern = &self.msaa_pattern;
+        let shadow = self.shadow_map.as_ref();
+        let sampler = self.sampler;
+        thread::scope(|s| {
+            for _ in 0..options.threads.max(1) {
+                s.spawn(|| loop {
+                    let itile = next.fetch_add(1, Ordering::Relaxed);
+                    if itile >= tiles.len() {
+                        break;
+                    }
+                    let mut tile = tiles[itile].lock().unwrap();
+                    for &itri in &bins[itile] {
+                        let tri = &tris[itri];
+                        let ctx = FragmentContext::new(tri.material, shadow, sampler);
+                        tile.rasterize_tri(
+                            &tri.pts,
+                            tri.bary.as_ref(),
+                            tri.shader.as_ref(),
+                            tri.model,
+                            &ctx,
+                            pattern,
+                        );
+                    }
+                });
+            }
+        });
+
+        for tile in tiles {
+            tile.into_inner().unwrap().merge_into(&mut self.frame);
+        }
+    }
+
+    // 先在齐次空间中裁剪, 再剔除背面与退化三角形, 最后逐个光栅化子三角形
+    // 裁剪后所有顶点w>0, 屏幕空间绕序才有意义
+    pub fn rasterize_tri(
+        &mut self,
+        pts: &Vector3<Vector4<f32>>,
+        shader: &dyn ShaderPayload,
+        model: &Objcracker,
+        material: &Material,
+    ) {
+        let ctx = FragmentContext::new(material, self.shadow_map.as_ref(), self.sampler);
+        for clipped in clip_triangle(pts, &self.clip_planes) {
+            if culled(&clipped.pts, self.cull_mode, self.front_face) {
+                continue;
+            }
+            self.frame.rasterize_tri(
+                &clipped.pts,
+                clipped.bary.as_ref(),
+                shader,
+                model,
+                &ctx,
+                &self.msaa_pattern,
+            );
+        }
+    }
+
+    // 开启MSAA时将采样缓冲解析到帧缓冲, 依次执行HDR后处理, 色调映射与sRGB编码, 显示后处理
+    // 结果写入显示缓冲, 每帧绘制完成后调用
+    pub fn resolve(&mut self) {
+        self.frame.resolve();
+        let mut color: Vec<Vector3<f32>> =
+            self.frame.f_buffer().iter().map(|c| c / 255.0).collect();
+        let mut frame = PostFrame::new(
+            self.width,
+            self.height,
+            &mut color,
+            self.frame.d_buffer(),
+            &(self.m_viewport * self.m_projection),
+        );
+        self.post.run(PostStage::Hdr, &mut frame);
+        for c in frame.colors_mut() {
+            *c = self.tone.apply(c);
+        }
+        self.post.run(PostStage::Display, &mut frame);
+        for (pixel, c) in self.display.iter_mut().zip(color.iter()) {
+            *pixel = from_unit_rgb(c);
+        }
+    }
+
+    /////////////////////////////////////////////////////////////////////////////////
+
+    // 清屏颜色按sRGB(0~255)给出, 转到线性空间后写入帧缓冲
+    pub fn clear_frame_buff(&mut self, color: &Vector3<f32>) {
+        let linear = color.map(|c| srgb_to_linear((c / 255.0).clamp(0.0, 1.0)) * 255.0);
+        self.frame.clear_color(&linear);
+    }
+
+    pub fn clear_depth_buff(&mut self) {
+        self.frame.clear_depth();
+    }
+
+    pub fn set_color(&mut self, x: usize, y: usize, color: &Vector3<f32>) {
+        self.frame.set_color(x, y, color);
+    }
+
+    pub fn get_depth(&self, x: usize, y: usize) -> f32 {
+        self.frame.get_depth(x, y)
+    }
+
+    pub fn set_depth(&mut self, x: usize, y: usize, value: f32) {
+        self.frame.set_depth(x, y, value);
+    }
+
+    /////////////////////////////////////////////////////////////////////////////////
+
+    // 按扩展名(png/tga)编码当前显示缓冲
+    pub fn save_frame<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        let format = ImageFormat::from_path(&path)?;
+        if format != ImageFormat::Png && format != ImageFormat::Tga {
+            return Err(ImageError::Unsupported(
+                UnsupportedError::from_format_and_kind(
+                    ImageFormatHint::Exact(format),
+                    UnsupportedErrorKind::Format(ImageFormatHint::Exact(format)),
+                ),
+            ));
+        }
+        let mut raw: Vec<u8> = Vec::with_capacity(self.width * self.height * 3);
+        for pixel in &self.display {
+            raw.push((pixel >> 16) as u8);
+            raw.push((pixel >> 8) as u8);
+            raw.push(*pixel as u8);
+        }
+        let img: ImageBuffer<Rgb<u8>, Vec<u8>> =
+            ImageBuffer::from_raw(self.width as u32, self.height as u32, raw).unwrap();
+        img.save_with_format(path, format)
+    }
+
+    /////////////////////////////////////////////////////////////////////////////////
+
+    #[inline]
+    pub fn enable_msaa(&mut self, option: MsaaOptions) {
+        self.msaa_status = option;
+        self.msaa_pattern = option.sample_pattern();
+        self.frame.set_msaa_samples(option.samples());
+    }
+    #[inline]
+    pub fn disable_msaa(&mut self) {
+        self.enable_msaa(MsaaOptions::Disable);
+    }
+    #[inline]
+    pub fn msaa_status(&self) -> &MsaaOptions {
+        &self.msaa_status
+    }
+    #[inline]
+    pub fn set_cull_mode(&mut self, mode: CullMode, front_face: FrontFace) {
+        self.cull_mode = mode;
+        self.front_face = front_face;
+    }
+    #[inline]
+    pub fn cull_mode(&self) -> CullMode {
+        self.cull_mode
+    }
+    #[inline]
+    pub fn front_face(&self) -> FrontFace {
+        self.front_face
+    }
+    #[inline]
+    pub fn enable_tiling(&mut self, option: TileOptions) {
+        self.tile_status = Some(option);
+    }
+    #[inline]
+    pub fn disable_tiling(&mut self) {
+        self.tile_status = None;
+    }
+    #[inline]
+    pub fn tile_status(&self) -> Option<&TileOptions> {
+        self.tile_status.as_ref()
+    }
+    #[inline]
+    pub fn enable_shadow(&mut self, option: ShadowOptions) {
+        self.shadow_map = Some(ShadowMap::new(option));
+    }
+    #[inline]
+    pub fn disable_shadow(&mut self) {
+        self.shadow_map = None;
+    }
+    #[inline]
+    pub fn shadow_status(&self) -> Option<&ShadowOptions> {
+        self.shadow_map.as_ref().map(ShadowMap::options)
+    }
+    #[inline]
+    pub fn set_tone_mapping(&mut self, option: ToneOptions) {
+        self.tone = option;
+    }
+    #[inline]
+    pub fn tone_mapping(&self) -> &ToneOptions {
+        &self.tone
+    }
+    #[inline]
+    pub fn set_post_chain(&mut self, chain: PostChain) {
+        self.post = chain;
+    }
+    #[inline]
+    pub fn post_chain(&self) -> &PostChain {
+        &self.post
+    }
+    #[inline]
+    pub fn post_chain_mut(&mut self) -> &mut PostChain {
+        &mut self.post
+    }
+    #[inline]
+    pub fn set_sampler(&mut self, sampler: Sampler) {
+        self.sampler = sampler;
+    }
+    #[inline]
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+    #[inline]
+    pub fn transform(&self) -> Matrix4<f32> {
+        self.m_viewport * self.m_projection * self.m_camera * self.m_model
+    }
+    #[inline]
+    pub fn viewport(&self) -> &Matrix4<f32> {
+        &self.m_viewport
+    }
+    #[inline]
+    pub fn projection(&self) -> &Matrix4<f32> {
+        &self.m_projection
+    }
+    #[inline]
+    pub fn camera(&self) -> &Matrix4<f32> {
+        &self.m_camera
+    }
+    #[inline]
+    pub fn model(&self) -> &Matrix4<f32> {
+        &self.m_model
+    }
+    #[inline]
+    pub fn set_viewport(&mut self, m: Matrix4<f32>) {
+        self.m_viewport = m;
+    }
+    #[inline]
+    pub fn set_projection(&mut self, m: Matrix4<f32>) {
+        self.m_projection = m;
+    }
+    #[inline]
+    pub fn set_camera(&mut self, m: Matrix4<f32>) {
+        self.m_camera = m;
+    }
+    // 世界空间中的相机位置, 需与视图矩阵一同设置, 着色器由此求视线方向
+    #[inline]
+    pub fn set_eye(&mut self, eye: &Vector3<f32>) {
+        self.eye = *eye;
+    }
+    #[inline]
+    pub fn eye(&self) -> &Vector3<f32> {
+        &self.eye
+    }
+    #[inline]
+    pub fn model_it(&self) -> &Matrix4<f32> {
+        &self.m_model_it
+    }
+    #[inline]
+    pub fn set_model(&mut self, m: Matrix4<f32>) {
+        self.m_model_it = graphics::calc_m_inverse_transpose(&m);
+        self.m_model = m;
+    }
+    #[inline]
+    pub fn get_frame_buff(&self) -> &Vec<u32> {
+        &self.display
+    }
+    #[inline]
+    pub fn w(self) -> usize {
+        self.width
+    }
+    #[inline]
+    pub fn h(self) -> usize {
+        self.height
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use std::fs;
+
+    use super::*;
+    use crate::scene::Scene;
+    use crate::shader::ShaderKind;
+
+    // 经纬划分的单位球, 面片朝外
+    fn sphere(nlon: usize, nlat: usize) -> String {
+        let mut text = String::new();
+        for ilat in 0..=nlat {
+            for ilon in 0..nlon {
+                let (u, v) = (ilon as f32 / nlon as f32, ilat as f32 / nlat as f32);
+                let (sin, cos) = (PI * v).sin_cos();
+                let p = Vector3::new(sin * (2.0 * PI * u).cos(), cos, sin * (2.0 * PI * u).sin());
+                text += &format!("v {} {} {}\nvt {} {}\n", p.x, p.y, p.z, u, v);
+            }
+        }
+        for ilat in 0..nlat {
+            for ilon in 0..nlon {
+                let a = ilat * nlon + ilon + 1;
+                let b = ilat * nlon + (ilon + 1) % nlon + 1;
+                let (c, d) = (a + nlon, b + nlon);
+                text += &format!("f {0}/{0} {1}/{1} {2}/{2} {3}/{3}\n", a, b, d, c);
+            }
+        }
+        text
+    }
+
+    // 写入临时文件后载入, mtl不为空时同时写出同名材质库
+    fn load(name: &str, obj: &str, mtl: Option<&str>) -> Objcracker {
+        let prefix = std::env::temp_dir().join(format!("delusion_{}_{}", std::process::id(), name));
+        let prefix = prefix.to_str().unwrap();
+        let files = [
+            (format!("{}.obj", prefix), Some(obj)),
+            (format!("{}.mtl", prefix), mtl),
+        ];
+        for (file, text) in files.iter() {
+            if let Some(text) = text {
+                fs::write(file, text).unwrap();
+            }
+        }
+        let mut model = Objcracker::new(prefix);
+        let result = model.interpret();
+        for (file, text) in files.iter() {
+            if text.is_some() {
+                fs::remove_file(file).unwrap();
+            }
+        }
+        result.unwrap();
+        model
+    }
+
+    // 开启阴影, 两个绘制项相互遮挡, 画布尺寸不是区块大小的整数倍
+    fn render(model: &Objcracker, msaa: MsaaOptions, tiling: Option<TileOptions>) -> Delusion {
+        let mut scene = Scene::new();
+        scene.settings.width = 70;
+        scene.settings.height = 45;
+        scene.settings.msaa = msaa;
+        scene.settings.tiling = false;
+        scene.settings.shadow = Some(ShadowOptions::new(128, 3.0, 1, 2.0));
+        let mut d = scene.renderer();
+        if let Some(options) = tiling {
+            d.enable_tiling(options);
+        }
+        let offset = Matrix4::new_translation(&Vector3::new(0.6, 0.2, -0.5));
+        let items = [
+            DrawItem::new(model, Matrix4::identity(), 0),
+            DrawItem::new(model, offset, 1),
+        ];
+        let mut shaders = vec![ShaderKind::Gouraud.build(), ShaderKind::Pbr.build()];
+        d.clear_frame_buff(&scene.settings.background);
+        d.clear_depth_buff();
+        d.draw_items(&items, &mut shaders, &scene.lights);
+        d.resolve();
+        d
+    }
+
+    #[test]
+    fn tiled_matches_serial() {
+        let ground = "v -2 -0.5 -2\nv 2 -0.5 -2\nv 2 -0.5 2\nv -2 -0.5 2\nf -1 -2 -3 -4\n";
+        let model = load("tiles", &(sphere(12, 8) + ground), None);
+        for msaa in [MsaaOptions::Disable, MsaaOptions::X4].iter() {
+            let serial = render(&model, *msaa, None);
+            assert!(serial.frame.d_buffer().iter().any(|&d| d > f32::MIN));
+            // 奇数大小的区块使2x2像素块跨越区块边界
+            for options in [TileOptions::new(16, 1), TileOptions::new(7, 4)].iter() {
+                let tiled = render(&model, *msaa, Some(*options));
+                assert!(
+                    serial.frame.f_buffer() == tiled.frame.f_buffer(),
+                    "color differs with msaa {} and {}",
+                    msaa,
+                    options
+                );
+                assert!(
+                    serial.frame.d_buffer() == tiled.frame.d_buffer(),
+                    "depth differs with msaa {} and {}",
+                    msaa,
+                    options
+                );
+                assert_eq!(serial.display, tiled.display);
+            }
+        }
+    }
+    // 镜面高光应出现在法线为光源方向与视线方向的半程向量之处, 视线方向由真实的相机位置求得
+    #[test]
+    fn specular_highlight_faces_camera() {
+        let name = format!("delusion_{}_shiny", std::process::id());
+        let obj = format!("mtllib {}.mtl\nusemtl shiny\n{}", name, sphere(64, 32));
+        let mtl = "newmtl shiny\nKd 0.05 0.05 0.05\nPr 0.2\nPm 0\n";
+        let model = load("shiny", &obj, Some(mtl));
+
+        let mut scene = Scene::new();
+        scene.settings.width = 96;
+        scene.settings.height = 96;
+        scene.settings.shadow = None;
+        scene.settings.tiling = false;
+        let light = Vector3::new(1.0, 1.0, 1.0).normalize();
+        scene.lights = vec![Light::directional(&light)];
+        let mut d = scene.renderer();
+        let radius = 0.5;
+        let items = [DrawItem::new(&model, Matrix4::new_scaling(radius), 0)];
+        let mut shaders = vec![ShaderKind::Pbr.build()];
+        d.clear_frame_buff(&Vector3::repeat(0.0));
+        d.clear_depth_buff();
+        d.draw_items(&items, &mut shaders, &scene.lights);
+
+        // 球面上法线为n的点位于radius * n, 迭代求n = normalize(l + v)
+        let eye = scene.camera.eye;
+        let mut n: Vector3<f32> = (light + eye.normalize()).normalize();
+        for _ in 0..16 {
+            n = (light + (eye - n * radius).normalize()).normalize();
+        }
+        let p = n * radius;
+        let q = d.transform() * Vector4::new(p.x, p.y, p.z, 1.0);
+        let expected = q.xy() / q.w;
+
+        let (w, h) = (scene.settings.width, scene.settings.height);
+        let colors = d.frame.f_buffer();
+        let brightest = (0..w * h)
+            .max_by(|&a, &b| colors[a].sum().partial_cmp(&colors[b].sum()).unwrap())
+            .unwrap();
+        // 颜色缓冲自上而下存储
+        let found = Vector2::new((brightest % w) as f32, (h - 1 - brightest / w) as f32);
+        assert!(colors[brightest].sum() > 255.0);
+        assert!(
+            (found - expected).norm() < 2.0,
+            "highlight at {}, expected {}",
+            found,
+            expected
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use nalgebra::{Matrix4, UnitQuaternion, Vector3};
+
+use objcracker::Objcracker;
+
+use crate::delusion::DrawItem;
+use crate::graphics;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 局部变换, 作用顺序为缩放, 旋转, 平移
+#[derive(Clone, Copy)]
+pub struct Transform {
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Transform {
+    pub fn new(
+        translation: Vector3<f32>,
+        rotation: UnitQuaternion<f32>,
+        scale: Vector3<f32>,
+    ) -> Transform {
+        Transform {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn identity() -> Transform {
+        Transform::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            UnitQuaternion::identity(),
+            Vector3::new(1.0, 1.0, 1.0),
+        )
+    }
+
+    // 依次绕x, y, z轴旋转的角度(角度制)
+    pub fn euler_degrees(angles: &Vector3<f32>) -> UnitQuaternion<f32> {
+        let r = angles.map(graphics::degree_to_radian);
+        UnitQuaternion::from_euler_angles(r.x, r.y, r.z)
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::new_translation(&self.translation)
+            * self.rotation.to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::identity()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// model为模型列表中的下标, 为空的节点只用于组织子节点的变换
+struct GraphNode {
+    local: Transform,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    model: Option<usize>,
+    shader: usize,
+    world: Matrix4<f32>,
+    world_it: Matrix4<f32>,
+    dirty: bool,
+}
+
+// 节点按添加顺序存放, 父节点总在子节点之前, 按下标顺序更新即可保证父节点先于子节点
+// 世界矩阵及其逆转置只在局部变换改变后重新计算
+pub struct SceneGraph {
+    nodes: Vec<GraphNode>,
+}
+
+impl SceneGraph {
+    pub fn new() -> SceneGraph {
+        SceneGraph { nodes: Vec::new() }
+    }
+
+    // parent必须是已存在的节点
+    pub fn add_node(&mut self, parent: Option<usize>, local: Transform) -> usize {
+        let id = self.nodes.len();
+        if let Some(p) = parent {
+            assert!(p < id, "parent node {} does not exist", p);
+            self.nodes[p].children.push(id);
+        }
+        self.nodes.push(GraphNode {
+            local,
+            parent,
+            children: Vec::new(),
+            model: None,
+            shader: 0,
+            world: Matrix4::<f32>::identity(),
+            world_it: Matrix4::<f32>::identity(),
+            dirty: true,
+        });
+        id
+    }
+
+    // 将模型挂到节点上, shader为绘制时着色器列表中的下标
+    #[inline]
+    pub fn attach_model(&mut self, id: usize, model: usize, shader: usize) {
+        self.nodes[id].model = Some(model);
+        self.nodes[id].shader = shader;
+    }
+
+    // 修改局部变换后该节点及其所有子孙节点的世界矩阵都需要重新计算
+    pub fn set_local(&mut self, id: usize, local: Transform) {
+        self.nodes[id].local = local;
+        let mut stack: Vec<usize> = vec![id];
+        while let Some(i) = stack.pop() {
+            self.nodes[i].dirty = true;
+            stack.extend_from_slice(&self.nodes[i].children);
+        }
+    }
+
+    pub fn update_world(&mut self) {
+        for i in 0..self.nodes.len() {
+            if !self.nodes[i].dirty {
+                continue;
+            }
+            let local = self.nodes[i].local.matrix();
+            let world = match self.nodes[i].parent {
+                Some(p) => self.nodes[p].world * local,
+                None => local,
+            };
+            let node = &mut self.nodes[i];
+            node.world = world;
+            node.world_it = graphics::calc_m_inverse_transpose(&world);
+            node.dirty = false;
+        }
+    }
+
+    // 按节点顺序收集挂有模型的节点, 调用前需先update_world
+    pub fn draw_items<'a>(&self, models: &'a [Objcracker]) -> Vec<DrawItem<'a>> {
+        self.nodes
+            .iter()
+            .filter_map(|node| {
+                node.model.map(|imodel| DrawItem {
+                    model: &models[imodel],
+                    transform: node.world,
+                    transform_it: node.world_it,
+                    shader: node.shader,
+                })
+            })
+            .collect()
+    }
+
+    #[inline]
+    pub fn local(&self, id: usize) -> &Transform {
+        &self.nodes[id].local
+    }
+    #[inline]
+    pub fn world(&self, id: usize) -> &Matrix4<f32> {
+        &self.nodes[id].world
+    }
+    #[inline]
+    pub fn parent(&self, id: usize) -> Option<usize> {
+        self.nodes[id].parent
+    }
+    #[inline]
+    pub fn children(&self, id: usize) -> &[usize] {
+        &self.nodes[id].children
+    }
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl Default for SceneGraph {
+    fn default() -> SceneGraph {
+        SceneGraph::new()
+    }
+}
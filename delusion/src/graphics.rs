@@ -0,0 +1,767 @@
+use std::fmt::{Display, Error, Formatter};
+
+use nalgebra::{Matrix3, Matrix4, Unit, Vector2, Vector3, Vector4};
+
+use objcracker::linear_to_srgb;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// D3D标准多重采样位置, 以1/16像素为单位, 相对像素中心
+static MSAA_PATTERN_X2: [(i8, i8); 2] = [(4, 4), (-4, -4)];
+static MSAA_PATTERN_X4: [(i8, i8); 4] = [(-2, -6), (6, -2), (-6, 2), (2, 6)];
+static MSAA_PATTERN_X8: [(i8, i8); 8] = [
+    (1, -3),
+    (-1, 3),
+    (5, 1),
+    (-3, -5),
+    (-5, 5),
+    (-7, -1),
+    (3, 7),
+    (7, -7),
+];
+static MSAA_PATTERN_X16: [(i8, i8); 16] = [
+    (1, 1),
+    (-1, -3),
+    (-3, 2),
+    (4, -1),
+    (-5, -2),
+    (2, 5),
+    (5, 3),
+    (3, -5),
+    (-2, 6),
+    (0, -7),
+    (-4, -6),
+    (-6, 4),
+    (-8, 0),
+    (7, -4),
+    (6, 7),
+    (-7, -8),
+];
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 整帧(或区块)的子采样缓冲, 每个像素连续存放samples个采样点的深度与颜色
+#[derive(Clone)]
+pub struct MsaaTensor {
+    samples: usize,
+    dept: Vec<f32>,
+    colo: Vec<Vector3<f32>>,
+}
+
+impl MsaaTensor {
+    pub fn new(npixels: usize, samples: usize) -> MsaaTensor {
+        MsaaTensor {
+            samples,
+            dept: vec![f32::MIN; npixels * samples],
+            colo: vec![Vector3::repeat(0.0); npixels * samples],
+        }
+    }
+    #[inline]
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+    #[inline]
+    pub fn set_dept(&mut self, ipixel: usize, isample: usize, value: f32) {
+        self.dept[ipixel * self.samples + isample] = value;
+    }
+    #[inline]
+    pub fn dept(&self, ipixel: usize, isample: usize) -> f32 {
+        self.dept[ipixel * self.samples + isample]
+    }
+    #[inline]
+    pub fn set_colo(&mut self, ipixel: usize, isample: usize, color: &Vector3<f32>) {
+        self.colo[ipixel * self.samples + isample] = *color;
+    }
+    pub fn clear_colo(&mut self, color: &Vector3<f32>) {
+        self.colo.fill(*color);
+    }
+    pub fn clear_dept(&mut self) {
+        self.dept.fill(f32::MIN);
+    }
+
+    // 从src的第src_pixel个像素起拷贝npixels个像素的全部采样点
+    pub fn copy_pixels(
+        &mut self,
+        dst_pixel: usize,
+        src: &MsaaTensor,
+        src_pixel: usize,
+        npixels: usize,
+    ) {
+        let n = npixels * self.samples;
+        let (dst, src_start) = (dst_pixel * self.samples, src_pixel * src.samples);
+        self.dept[dst..dst + n].copy_from_slice(&src.dept[src_start..src_start + n]);
+        self.colo[dst..dst + n].copy_from_slice(&src.colo[src_start..src_start + n]);
+    }
+
+    // 解析: 颜色取所有采样点的平均, 深度取最近的采样点
+    pub fn resolve(&self, ipixel: usize) -> (Vector3<f32>, f32) {
+        let range = ipixel * self.samples..(ipixel + 1) * self.samples;
+        let color: Vector3<f32> = self.colo[range.clone()].iter().sum();
+        let depth = self.dept[range].iter().cloned().fold(f32::MIN, f32::max);
+        (color / self.samples as f32, depth)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MsaaOptions {
+    Disable,
+    X2,
+    X4,
+    X8,
+    X16,
+}
+
+impl MsaaOptions {
+    pub fn samples(&self) -> usize {
+        match self {
+            MsaaOptions::Disable => 0,
+            MsaaOptions::X2 => 2,
+            MsaaOptions::X4 => 4,
+            MsaaOptions::X8 => 8,
+            MsaaOptions::X16 => 16,
+        }
+    }
+
+    // 0与1均表示关闭, 不支持的采样数返回None
+    pub fn from_samples(samples: usize) -> Option<MsaaOptions> {
+        match samples {
+            0 | 1 => Some(MsaaOptions::Disable),
+            2 => Some(MsaaOptions::X2),
+            4 => Some(MsaaOptions::X4),
+            8 => Some(MsaaOptions::X8),
+            16 => Some(MsaaOptions::X16),
+            _ => None,
+        }
+    }
+
+    // 采样点相对像素中心的偏移, 单位为像素
+    // D3D的y轴向下, 而光栅化坐标y轴向上, 需翻转
+    pub fn sample_pattern(&self) -> Vec<Vector2<f32>> {
+        let pattern: &[(i8, i8)] = match self {
+            MsaaOptions::Disable => &[],
+            MsaaOptions::X2 => &MSAA_PATTERN_X2,
+            MsaaOptions::X4 => &MSAA_PATTERN_X4,
+            MsaaOptions::X8 => &MSAA_PATTERN_X8,
+            MsaaOptions::X16 => &MSAA_PATTERN_X16,
+        };
+        pattern
+            .iter()
+            .map(|&(x, y)| Vector2::new(x as f32 / 16.0, -y as f32 / 16.0))
+            .collect()
+    }
+}
+
+impl Display for MsaaOptions {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            MsaaOptions::Disable => write!(f, "Disable"),
+            option => write!(f, "{}x", option.samples()),
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CullMode {
+    None,
+    Back,
+    Front,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FrontFace {
+    Ccw,
+    Cw,
+}
+
+impl Display for CullMode {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let info = match self {
+            CullMode::None => "None",
+            CullMode::Back => "Back",
+            CullMode::Front => "Front",
+        };
+        write!(f, "{}", info)
+    }
+}
+
+// 屏幕空间(y轴向上)中三角形有向面积的两倍, 即barycentric中的分母, 逆时针为正
+#[inline(always)]
+pub fn signed_area(pts: &Vector3<Vector4<f32>>) -> f32 {
+    let (a, b, c) = (pts[0] / pts[0][3], pts[1] / pts[1][3], pts[2] / pts[2][3]);
+    (b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y)
+}
+
+// 退化三角形总是剔除, 其余按剔除模式与正面绕序判断
+pub fn culled(pts: &Vector3<Vector4<f32>>, mode: CullMode, front_face: FrontFace) -> bool {
+    let area = signed_area(pts);
+    if area == 0.0 || !area.is_finite() {
+        return true;
+    }
+    let front = match front_face {
+        FrontFace::Ccw => area > 0.0,
+        FrontFace::Cw => area < 0.0,
+    };
+    match mode {
+        CullMode::None => false,
+        CullMode::Back => !front,
+        CullMode::Front => front,
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct TileOptions {
+    pub tile_size: usize,
+    pub threads: usize,
+}
+
+impl TileOptions {
+    pub fn new(tile_size: usize, threads: usize) -> TileOptions {
+        TileOptions {
+            tile_size: tile_size.max(1),
+            threads: threads.max(1),
+        }
+    }
+
+    // 64x64区块, 线程数取可用核心数
+    pub fn auto() -> TileOptions {
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        TileOptions::new(64, threads)
+    }
+}
+
+impl Display for TileOptions {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(
+            f,
+            "{}x{} tiles/{} threads",
+            self.tile_size, self.tile_size, self.threads
+        )
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 阴影贴图参数: size为深度图边长, extent为光源正交视锥覆盖的场景半径
+// bias以深度缓冲单位(0~255)计, pcf为百分比渐近过滤的核半径, 0表示不过滤
+#[derive(Clone, Copy)]
+pub struct ShadowOptions {
+    pub size: usize,
+    pub bias: f32,
+    pub pcf: usize,
+    pub extent: f32,
+}
+
+impl ShadowOptions {
+    pub fn new(size: usize, bias: f32, pcf: usize, extent: f32) -> ShadowOptions {
+        ShadowOptions {
+            size: size.max(1),
+            bias: bias.max(0.0),
+            pcf,
+            extent: extent.max(f32::EPSILON),
+        }
+    }
+}
+
+impl Default for ShadowOptions {
+    fn default() -> ShadowOptions {
+        ShadowOptions::new(1024, 3.0, 1, 2.0)
+    }
+}
+
+impl Display for ShadowOptions {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(
+            f,
+            "{}x{} shadow/bias {}/pcf {}",
+            self.size, self.size, self.bias, self.pcf
+        )
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 色调映射算子, 将线性HDR颜色压缩到显示范围[0, 1]
+// None直接截断, Exposure为 1 - e^(-c), Aces为Narkowicz拟合的ACES电影曲线
+#[derive(Clone, Copy, PartialEq)]
+pub enum ToneMap {
+    None,
+    Reinhard,
+    Aces,
+    Exposure,
+}
+
+impl ToneMap {
+    pub fn from_name(name: &str) -> Option<ToneMap> {
+        match name {
+            "none" => Some(ToneMap::None),
+            "reinhard" => Some(ToneMap::Reinhard),
+            "aces" => Some(ToneMap::Aces),
+            "exposure" => Some(ToneMap::Exposure),
+            _ => None,
+        }
+    }
+
+    // 单个通道的映射, c为线性值, 1.0为白
+    #[inline]
+    pub fn map(&self, c: f32) -> f32 {
+        let c = c.max(0.0);
+        let mapped = match self {
+            ToneMap::None => c,
+            ToneMap::Reinhard => c / (1.0 + c),
+            ToneMap::Aces => (c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14),
+            ToneMap::Exposure => 1.0 - (-c).exp(),
+        };
+        mapped.min(1.0)
+    }
+}
+
+impl Display for ToneMap {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let info = match self {
+            ToneMap::None => "None",
+            ToneMap::Reinhard => "Reinhard",
+            ToneMap::Aces => "ACES",
+            ToneMap::Exposure => "Exposure",
+        };
+        write!(f, "{}", info)
+    }
+}
+
+// exposure为映射前乘到线性颜色上的曝光倍率
+#[derive(Clone, Copy, PartialEq)]
+pub struct ToneOptions {
+    pub operator: ToneMap,
+    pub exposure: f32,
+}
+
+impl ToneOptions {
+    pub fn new(operator: ToneMap, exposure: f32) -> ToneOptions {
+        ToneOptions {
+            operator,
+            exposure: exposure.max(0.0),
+        }
+    }
+
+    // 线性颜色(1.0为白)映射后按sRGB编码, 得到0~1的显示颜色
+    pub fn apply(&self, color: &Vector3<f32>) -> Vector3<f32> {
+        color.map(|c| linear_to_srgb(self.operator.map(c * self.exposure)))
+    }
+}
+
+impl Default for ToneOptions {
+    fn default() -> ToneOptions {
+        ToneOptions::new(ToneMap::None, 1.0)
+    }
+}
+
+impl Display for ToneOptions {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}/exposure {}", self.operator, self.exposure)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+pub fn bounding_box(pts: &Vector3<Vector4<f32>>, bboxmin: &mut [f32; 2], bboxmax: &mut [f32; 2]) {
+    for i in 0..3 {
+        for j in 0..2 {
+            bboxmin[j] = bboxmin[j].min(pts[i][j] / pts[i][3]);
+            bboxmax[j] = bboxmax[j].max(pts[i][j] / pts[i][3]);
+        }
+    }
+}
+
+// 光栅化时遍历的包围盒, pattern不为空时采样点偏离像素中心, 包围盒向外扩展一个像素
+pub fn sample_bounding_box(
+    pts: &Vector3<Vector4<f32>>,
+    pattern: &[Vector2<f32>],
+    bboxmin: &mut [f32; 2],
+    bboxmax: &mut [f32; 2],
+) {
+    bounding_box(pts, bboxmin, bboxmax);
+    if !pattern.is_empty() {
+        bboxmin[0] -= 1.0;
+        bboxmin[1] -= 1.0;
+        bboxmax[0] += 1.0;
+        bboxmax[1] += 1.0;
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+pub static CLIP_W_NEAR: f32 = 1e-3;
+// 视口变换后深度缓冲的取值范围为0~255, 越大越近
+pub static CLIP_Z_MAX: f32 = 255.0;
+
+// 齐次空间中的裁剪平面, 点v满足 normal·v + offset >= 0 时位于内侧
+#[derive(Clone, Copy)]
+pub struct ClipPlane {
+    normal: Vector4<f32>,
+    offset: f32,
+}
+
+impl ClipPlane {
+    pub fn new(normal: Vector4<f32>, offset: f32) -> ClipPlane {
+        ClipPlane { normal, offset }
+    }
+    #[inline]
+    pub fn distance(&self, v: &Vector4<f32>) -> f32 {
+        self.normal.dot(v) + self.offset
+    }
+}
+
+// 视口变换后的齐次坐标下的七个裁剪面: w近平面, 深度范围两端的z平面(0 <= z/w <= 255),
+// 以及带保护带的上下左右平面
+// 保护带内的三角形不做xy裁剪, 交由包围盒截断处理
+pub fn calc_clip_planes(width: usize, height: usize, guard: f32) -> Vec<ClipPlane> {
+    let (w, h) = (width as f32, height as f32);
+    vec![
+        ClipPlane::new(Vector4::new(0.0, 0.0, 0.0, 1.0), -CLIP_W_NEAR),
+        ClipPlane::new(Vector4::new(0.0, 0.0, 1.0, 0.0), 0.0),
+        ClipPlane::new(Vector4::new(0.0, 0.0, -1.0, CLIP_Z_MAX), 0.0),
+        ClipPlane::new(Vector4::new(1.0, 0.0, 0.0, guard * w), 0.0),
+        ClipPlane::new(Vector4::new(-1.0, 0.0, 0.0, w + guard * w), 0.0),
+        ClipPlane::new(Vector4::new(0.0, 1.0, 0.0, guard * h), 0.0),
+        ClipPlane::new(Vector4::new(0.0, -1.0, 0.0, h + guard * h), 0.0),
+    ]
+}
+
+// 子三角形顶点相对原三角形的重心坐标(按列), clip为齐次空间中的坐标, screen为屏幕空间中的坐标
+// 片元权重左乘对应矩阵即还原为原三角形上的权重, 着色器中的varying无需改动
+#[derive(Clone, Copy)]
+pub struct ClipBary {
+    pub clip: Matrix3<f32>,
+    pub screen: Matrix3<f32>,
+}
+
+pub struct ClippedTri {
+    pub pts: Vector3<Vector4<f32>>,
+    pub bary: Option<ClipBary>,
+}
+
+pub fn clip_triangle(pts: &Vector3<Vector4<f32>>, planes: &[ClipPlane]) -> Vec<ClippedTri> {
+    if planes
+        .iter()
+        .all(|p| (0..3).all(|i| p.distance(&pts[i]) >= 0.0))
+    {
+        return vec![ClippedTri {
+            pts: *pts,
+            bary: None,
+        }];
+    }
+
+    let mut poly: Vec<(Vector4<f32>, Vector3<f32>)> = (0..3)
+        .map(|i| {
+            let mut b: Vector3<f32> = Vector3::zeros();
+            b[i] = 1.0;
+            (pts[i], b)
+        })
+        .collect();
+    for plane in planes {
+        if poly.is_empty() {
+            break;
+        }
+        let mut clipped: Vec<(Vector4<f32>, Vector3<f32>)> = Vec::with_capacity(poly.len() + 1);
+        for i in 0..poly.len() {
+            let (a, b) = (&poly[i], &poly[(i + 1) % poly.len()]);
+            let (da, db) = (plane.distance(&a.0), plane.distance(&b.0));
+            if da >= 0.0 {
+                clipped.push(*a);
+            }
+            if (da >= 0.0) != (db >= 0.0) {
+                let t = da / (da - db);
+                clipped.push((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t));
+            }
+        }
+        poly = clipped;
+    }
+
+    let w: Vector3<f32> = Vector3::new(pts[0][3], pts[1][3], pts[2][3]);
+    let to_screen = |b: &Vector3<f32>| b.component_mul(&w) / b.dot(&w);
+    let mut tris: Vec<ClippedTri> = Vec::new();
+    for i in 1..poly.len().saturating_sub(1) {
+        let (a, b, c) = (&poly[0], &poly[i], &poly[i + 1]);
+        let screen = [to_screen(&a.1), to_screen(&b.1), to_screen(&c.1)];
+        tris.push(ClippedTri {
+            pts: Vector3::new(a.0, b.0, c.0),
+            bary: Some(ClipBary {
+                clip: Matrix3::from_columns(&[a.1, b.1, c.1]),
+                screen: Matrix3::from_columns(&screen),
+            }),
+        });
+    }
+    tris
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[inline(always)]
+pub fn from_u8_rgb(r: u8, g: u8, b: u8) -> u32 {
+    let (r, g, b) = (r as u32, g as u32, b as u32);
+    (r << 16) | (g << 8) | b
+}
+
+// 0~1的显示颜色量化为8位
+#[inline(always)]
+pub fn from_unit_rgb(color: &Vector3<f32>) -> u32 {
+    let c = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    from_u8_rgb(c.x, c.y, c.z)
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[inline(always)]
+pub fn interior(weights: &Vector3<f32>) -> bool {
+    if weights.x < 0.0 || weights.y < 0.0 || weights.z < 0.0 {
+        return false;
+    }
+    true
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 片元着色器接收的插值权重: 透视校正(默认)或屏幕空间
+#[derive(Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    Perspective,
+    Screen,
+}
+
+// 由屏幕空间重心坐标与各顶点的w求透视校正后的权重
+#[inline(always)]
+pub fn perspective_correct(weights: &Vector3<f32>, pts: &Vector3<Vector4<f32>>) -> Vector3<f32> {
+    let pc: Vector3<f32> = Vector3::new(
+        weights.x / pts[0][3],
+        weights.y / pts[1][3],
+        weights.z / pts[2][3],
+    );
+    pc / (pc.x + pc.y + pc.z)
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[inline(always)]
+pub fn barycentric(
+    a: &Vector4<f32>,
+    b: &Vector4<f32>,
+    c: &Vector4<f32>,
+    x: f32,
+    y: f32,
+) -> Vector3<f32> {
+    // beta不再除以(b.x - a.x), 否则ab边竖直时整个三角形得到NaN权重
+    let denom: f32 = (b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y);
+    let gamma: f32 = ((b.x - a.x) * (y - a.y) - (x - a.x) * (b.y - a.y)) / denom;
+    let beta: f32 = ((x - a.x) * (c.y - a.y) - (c.x - a.x) * (y - a.y)) / denom;
+    let alpha = 1.0 - beta - gamma;
+    Vector3::new(alpha, beta, gamma)
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+pub fn calc_m_model(axis: Vector3<f32>, angle: f32, scale: f32) -> Matrix4<f32> {
+    let axis_unit: Unit<Vector3<f32>> = Unit::new_normalize(axis);
+    let m_rotate = Matrix4::from_axis_angle(&axis_unit, degree_to_radian(angle));
+    let mut m_scale = Matrix4::<f32>::identity();
+    for i in 0..2 {
+        m_scale[(i, i)] = scale;
+    }
+    m_rotate * m_scale
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 视图矩阵为 R * (p - origin), R的三行为相机基u, v, w, 观察点origin变换到原点
+pub fn calc_m_camera(e: &Vector3<f32>, origin: &Vector3<f32>, up: &Vector3<f32>) -> Matrix4<f32> {
+    let w: Vector3<f32> = -(origin - e).normalize();
+    let u: Vector3<f32> = up.cross(&w).normalize();
+    let v: Vector3<f32> = w.cross(&u).normalize();
+
+    let mut m: Matrix4<f32> = Matrix4::<f32>::identity();
+    for i in 0..3 {
+        m[(0, i)] = u[i];
+        m[(1, i)] = v[i];
+        m[(2, i)] = w[i];
+    }
+    m[(0, 3)] = -u.dot(origin);
+    m[(1, 3)] = -v.dot(origin);
+    m[(2, 3)] = -w.dot(origin);
+    m
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+pub fn calc_m_projection(coeff: f32) -> Matrix4<f32> {
+    let mut m: Matrix4<f32> = Matrix4::<f32>::identity();
+    m[(3, 2)] = coeff;
+    m
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 非正方形画布时按短边缩放并居中, 保持物体不被拉伸
+pub fn calc_m_viewport(width: usize, height: usize, factor: f32) -> Matrix4<f32> {
+    let side = width.min(height) as f32 * factor;
+    let x = (width as f32 - side) / 2.0;
+    let y = (height as f32 - side) / 2.0;
+    let w = side;
+    let h = side;
+    let mut m: Matrix4<f32> = Matrix4::<f32>::identity();
+    m[(0, 3)] = x + w / 2.0;
+    m[(1, 3)] = y + h / 2.0;
+    m[(2, 3)] = 255.0 / 2.0;
+    m[(0, 0)] = (w - 1.0) / 2.0;
+    m[(1, 1)] = (h - 1.0) / 2.0;
+    m[(2, 2)] = 255.0 / 2.0;
+    m
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 法线变换矩阵, 不可逆的矩阵退化为单位阵
+pub fn calc_m_inverse_transpose(m: &Matrix4<f32>) -> Matrix4<f32> {
+    m.try_inverse()
+        .map_or_else(Matrix4::<f32>::identity, |inv| inv.transpose())
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+pub fn degree_to_radian(angle: f32) -> f32 {
+    angle / 180.0 * std::f32::consts::PI
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn near_plane() -> Vec<ClipPlane> {
+        vec![ClipPlane::new(
+            Vector4::new(0.0, 0.0, 0.0, 1.0),
+            -CLIP_W_NEAR,
+        )]
+    }
+
+    fn triangle(w: [f32; 3]) -> Vector3<Vector4<f32>> {
+        Vector3::new(
+            Vector4::new(10.0 * w[0], 10.0 * w[0], 100.0 * w[0], w[0]),
+            Vector4::new(90.0 * w[1], 10.0 * w[1], 100.0 * w[1], w[1]),
+            Vector4::new(50.0 * w[2], 90.0 * w[2], 100.0 * w[2], w[2]),
+        )
+    }
+
+    // 子三角形的顶点应等于原顶点按重心坐标的组合, 且每列重心坐标之和为1
+    fn assert_bary(pts: &Vector3<Vector4<f32>>, tri: &ClippedTri) {
+        let bary = tri.bary.expect("clipped triangle without weights");
+        for j in 0..3 {
+            let b: Vector3<f32> = bary.clip.column(j).into();
+            let p: Vector4<f32> = pts[0] * b.x + pts[1] * b.y + pts[2] * b.z;
+            assert!((p - tri.pts[j]).norm() < 1e-3, "{} != {}", p, tri.pts[j]);
+            assert!((b.sum() - 1.0).abs() < 1e-5);
+            assert!((bary.screen.column(j).sum() - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn msaa_resolve() {
+        let mut tensor = MsaaTensor::new(2, 4);
+        tensor.clear_colo(&Vector3::new(0.0, 0.0, 1.0));
+        tensor.set_colo(1, 0, &Vector3::new(1.0, 0.0, 0.0));
+        tensor.set_colo(1, 2, &Vector3::new(1.0, 0.0, 0.0));
+        tensor.set_dept(1, 0, 10.0);
+        tensor.set_dept(1, 2, 20.0);
+        // 颜色按采样点平均, 深度取最近
+        assert_eq!(tensor.resolve(1), (Vector3::new(0.5, 0.0, 0.5), 20.0));
+        assert_eq!(tensor.resolve(0), (Vector3::new(0.0, 0.0, 1.0), f32::MIN));
+    }
+
+    #[test]
+    fn tone_map_black_and_white() {
+        let operators = [
+            ToneMap::None,
+            ToneMap::Reinhard,
+            ToneMap::Aces,
+            ToneMap::Exposure,
+        ];
+        for operator in operators.iter() {
+            let black = ToneOptions::new(*operator, 1.0).apply(&Vector3::new(0.0, 0.0, 0.0));
+            assert_eq!(from_unit_rgb(&black), 0, "{}", operator);
+        }
+        // 帧缓冲中的255为线性的1.0, 不做映射时编码为白色
+        let white = ToneOptions::default().apply(&(Vector3::new(255.0, 255.0, 255.0) / 255.0));
+        assert_eq!(from_unit_rgb(&white), 0xffffff);
+    }
+
+    #[test]
+    fn tone_map_monotonic() {
+        for operator in [ToneMap::Reinhard, ToneMap::Aces].iter() {
+            let mut last = operator.map(0.0);
+            for i in 1..=200 {
+                let c = operator.map(i as f32 * 0.02);
+                assert!(c > last, "{} at {}: {} <= {}", operator, i, c, last);
+                last = c;
+            }
+            // 高光逐渐逼近白色而不超出
+            assert!(last < 1.0 && operator.map(1e6) <= 1.0);
+        }
+    }
+
+    #[test]
+    fn inside_triangle_is_untouched() {
+        let pts = triangle([1.0, 2.0, 0.5]);
+        let tris = clip_triangle(&pts, &calc_clip_planes(100, 100, 1.0));
+        assert_eq!(tris.len(), 1);
+        assert_eq!(tris[0].pts, pts);
+        assert!(tris[0].bary.is_none());
+    }
+
+    #[test]
+    fn one_vertex_behind_near_plane() {
+        let pts = triangle([1.0, 1.0, -1.0]);
+        let tris = clip_triangle(&pts, &near_plane());
+        assert_eq!(tris.len(), 2);
+        for tri in &tris {
+            assert_bary(&pts, tri);
+            assert!((0..3).all(|i| tri.pts[i].w >= CLIP_W_NEAR - 1e-6));
+        }
+        // 边 v1 -> v2 与近平面的交点
+        let t = (1.0 - CLIP_W_NEAR) / 2.0;
+        let expected = Vector3::new(0.0, 1.0 - t, t);
+        assert!(tris.iter().any(|tri| {
+            let clip = tri.bary.unwrap().clip;
+            (0..3).any(|j| (clip.column(j) - expected).norm() < 1e-5)
+        }));
+    }
+
+    #[test]
+    fn two_vertices_behind_near_plane() {
+        let pts = triangle([1.0, -1.0, -2.0]);
+        let tris = clip_triangle(&pts, &near_plane());
+        assert_eq!(tris.len(), 1);
+        assert_bary(&pts, &tris[0]);
+        let clip = tris[0].bary.unwrap().clip;
+        assert_eq!(clip.column(0), Vector3::new(1.0, 0.0, 0.0));
+        let t = (1.0 - CLIP_W_NEAR) / 2.0;
+        assert!((clip.column(1) - Vector3::new(1.0 - t, t, 0.0)).norm() < 1e-5);
+        let t = (1.0 - CLIP_W_NEAR) / 3.0;
+        assert!((clip.column(2) - Vector3::new(1.0 - t, 0.0, t)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn outside_triangle_is_dropped() {
+        let planes = calc_clip_planes(100, 100, 1.0);
+        assert!(clip_triangle(&triangle([-1.0, -2.0, -0.5]), &planes).is_empty());
+        let far_right = triangle([1.0, 1.0, 1.0]).map(|p| p + Vector4::new(500.0, 0.0, 0.0, 0.0));
+        assert!(clip_triangle(&far_right, &planes).is_empty());
+        let too_near = triangle([1.0, 1.0, 1.0]).map(|p| p + Vector4::new(0.0, 0.0, 300.0, 0.0));
+        assert!(clip_triangle(&too_near, &planes).is_empty());
+    }
+}
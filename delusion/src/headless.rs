@@ -0,0 +1,61 @@
+use image::ImageResult;
+use nalgebra::Vector3;
+
+use crate::delusion::{Delusion, DrawItem};
+use crate::light::Light;
+use crate::shader::ShaderPayload;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+pub struct HeadlessOptions {
+    pub frames: usize,
+    pub out: String,
+    pub clear_color: Vector3<f32>,
+}
+
+impl HeadlessOptions {
+    pub fn new(frames: usize, out: &str, clear_color: &Vector3<f32>) -> HeadlessOptions {
+        HeadlessOptions {
+            frames,
+            out: out.to_string(),
+            clear_color: *clear_color,
+        }
+    }
+
+    // 输出路径模板中的"{}"替换为帧序号, 无占位符时在扩展名前追加序号
+    pub fn frame_path(&self, iframe: usize) -> String {
+        if self.out.contains("{}") {
+            return self.out.replace("{}", &format!("{:04}", iframe));
+        }
+        if self.frames == 1 {
+            return self.out.clone();
+        }
+        match self.out.rfind('.') {
+            Some(dot) => format!("{}_{:04}{}", &self.out[..dot], iframe, &self.out[dot..]),
+            None => format!("{}_{:04}", self.out, iframe),
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 不创建窗口, 逐帧渲染并写出帧缓冲, 返回写出的文件路径
+pub fn render_headless(
+    d: &mut Delusion,
+    items: &[DrawItem],
+    shaders: &mut [Box<dyn ShaderPayload>],
+    lights: &[Light],
+    options: &HeadlessOptions,
+) -> ImageResult<Vec<String>> {
+    let mut paths = Vec::with_capacity(options.frames);
+    for iframe in 0..options.frames {
+        d.clear_frame_buff(&options.clear_color);
+        d.clear_depth_buff();
+        d.draw_items(items, shaders, lights);
+        d.resolve();
+        let path = options.frame_path(iframe);
+        d.save_frame(&path)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
@@ -0,0 +1,410 @@
+use std::fmt::{Display, Error, Formatter};
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 场景文件使用的最小JSON实现, 对象保留键的书写顺序
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    // 用于错误信息中描述实际类型
+    pub fn kind(&self) -> &'static str {
+        match self {
+            JsonValue::Null => "null",
+            JsonValue::Bool(_) => "boolean",
+            JsonValue::Number(_) => "number",
+            JsonValue::String(_) => "string",
+            JsonValue::Array(_) => "array",
+            JsonValue::Object(_) => "object",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct JsonError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+pub fn parse(text: &str) -> Result<JsonValue, JsonError> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+        line: 1,
+        column: 1,
+    };
+    parser.skip_whitespace();
+    let value = parser.nested(0)?;
+    parser.skip_whitespace();
+    match parser.peek() {
+        Some(c) => Err(parser.error(format!("unexpected `{}` after value", c))),
+        None => Ok(value),
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 嵌套过深的输入按语法错误处理, 避免递归耗尽栈空间
+static MAX_DEPTH: usize = 128;
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        match c == '\n' {
+            true => {
+                self.line += 1;
+                self.column = 1;
+            }
+            false => self.column += 1,
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: String) -> JsonError {
+        JsonError {
+            line: self.line,
+            column: self.column,
+            message,
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => Err(self.error(format!("expected `{}`, found `{}`", expected, c))),
+            None => Err(self.error(format!("expected `{}`, found end of input", expected))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ') | Some('\t') | Some('\n') | Some('\r') = self.peek() {
+            self.bump();
+        }
+    }
+
+    fn nested(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error(String::from("nesting too deep")));
+        }
+        match self.peek() {
+            Some('{') => self.object(depth),
+            Some('[') => self.array(depth),
+            Some('"') => Ok(JsonValue::String(self.string()?)),
+            Some('t') => self.literal("true", JsonValue::Bool(true)),
+            Some('f') => self.literal("false", JsonValue::Bool(false)),
+            Some('n') => self.literal("null", JsonValue::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(self.error(format!("unexpected `{}`", c))),
+            None => Err(self.error(String::from("unexpected end of input"))),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: JsonValue) -> Result<JsonValue, JsonError> {
+        for expected in word.chars() {
+            if self.peek() != Some(expected) {
+                return Err(self.error(format!("invalid literal, expected `{}`", word)));
+            }
+            self.bump();
+        }
+        Ok(value)
+    }
+
+    fn object(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        let mut entries: Vec<(String, JsonValue)> = Vec::new();
+        self.expect('{')?;
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(JsonValue::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error(String::from("expected string key")));
+            }
+            let key = self.string()?;
+            if entries.iter().any(|(k, _)| *k == key) {
+                return Err(self.error(format!("duplicate key `{}`", key)));
+            }
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+            let value = self.nested(depth + 1)?;
+            entries.push((key, value));
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => continue,
+                Some('}') => return Ok(JsonValue::Object(entries)),
+                _ => return Err(self.error(String::from("expected `,` or `}` in object"))),
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        let mut items: Vec<JsonValue> = Vec::new();
+        self.expect('[')?;
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.bump();
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            self.skip_whitespace();
+            items.push(self.nested(depth + 1)?);
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => continue,
+                Some(']') => return Ok(JsonValue::Array(items)),
+                _ => return Err(self.error(String::from("expected `,` or `]` in array"))),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        let mut s = String::new();
+        self.expect('"')?;
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.bump() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('u') => s.push(self.unicode_escape()?),
+                    _ => return Err(self.error(String::from("invalid escape sequence"))),
+                },
+                Some(c) if (c as u32) < 0x20 => {
+                    return Err(self.error(String::from("control character in string")))
+                }
+                Some(c) => s.push(c),
+                None => return Err(self.error(String::from("unterminated string"))),
+            }
+        }
+    }
+
+    // 代理对需由两个连续的\u转义组成
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high)
+                .ok_or_else(|| self.error(String::from("invalid unicode escape")));
+        }
+        if self.bump() != Some('\\') || self.bump() != Some('u') {
+            return Err(self.error(String::from("unpaired surrogate in unicode escape")));
+        }
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error(String::from("unpaired surrogate in unicode escape")));
+        }
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+            .ok_or_else(|| self.error(String::from("invalid unicode escape")))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let mut code: u32 = 0;
+        for _ in 0..4 {
+            let digit = self
+                .bump()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error(String::from("invalid unicode escape")))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    // 按JSON语法截取数字文本, 再交给标准库解析
+    fn number(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.pos;
+        if self.peek() == Some('-') {
+            self.bump();
+        }
+        match self.peek() {
+            Some('0') => {
+                self.bump();
+            }
+            Some(c) if c.is_ascii_digit() => self.digits(),
+            _ => return Err(self.error(String::from("invalid number"))),
+        }
+        if self.peek() == Some('.') {
+            self.bump();
+            if !matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+                return Err(self.error(String::from("invalid number")));
+            }
+            self.digits();
+        }
+        if let Some('e') | Some('E') = self.peek() {
+            self.bump();
+            if let Some('+') | Some('-') = self.peek() {
+                self.bump();
+            }
+            if !matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+                return Err(self.error(String::from("invalid number")));
+            }
+            self.digits();
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse::<f64>()
+            .map(JsonValue::Number)
+            .map_err(|_| self.error(format!("invalid number `{}`", text)))
+    }
+
+    fn digits(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+            self.bump();
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(text: &str) -> String {
+        match parse(text) {
+            Ok(JsonValue::String(s)) => s,
+            other => panic!("{:?}", other),
+        }
+    }
+
+    fn number(text: &str) -> f64 {
+        match parse(text) {
+            Ok(JsonValue::Number(n)) => n,
+            other => panic!("{:?}", other),
+        }
+    }
+
+    fn error(text: &str) -> JsonError {
+        parse(text).expect_err(text)
+    }
+
+    fn assert_error(text: &str, message: &str) {
+        let e = error(text);
+        assert!(e.message.contains(message), "{}: {}", text, e.message);
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            string(r#""a\"b\\c\/d\b\f\n\r\t""#),
+            "a\"b\\c/d\u{8}\u{c}\n\r\t"
+        );
+        assert_eq!(string(r#""\u00e9\u4E2D""#), "\u{e9}\u{4e2d}");
+        assert_eq!(string(r#""\ud83d\ude00!""#), "\u{1f600}!");
+        assert_error(r#""\x""#, "invalid escape");
+        assert_error(r#""\u12G4""#, "invalid unicode escape");
+        assert_error(r#""\ud83d""#, "unpaired surrogate");
+        assert_error(r#""\ud83dA""#, "unpaired surrogate");
+        assert_error(r#""\ude00""#, "invalid unicode escape");
+        assert_error("\"a\nb\"", "control character");
+        assert_error("\"abc", "unterminated");
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(number("0"), 0.0);
+        assert_eq!(number("-12"), -12.0);
+        assert_eq!(number("1e3"), 1000.0);
+        assert_eq!(number("-2.5E-2"), -0.025);
+        assert_eq!(number("0.5e+1"), 5.0);
+        for text in ["-", "1.", "1e", "1e+", ".5", "+1"].iter() {
+            assert!(parse(text).is_err(), "{}", text);
+        }
+        assert_error("01", "after value");
+    }
+
+    #[test]
+    fn trailing_garbage() {
+        let e = error("{} x");
+        assert_eq!((e.line, e.column), (1, 4));
+        assert!(e.message.contains("unexpected `x` after value"));
+        assert_error("[1, 2],", "after value");
+        assert!(parse("  [1, 2]\n\t ").is_ok());
+    }
+
+    #[test]
+    fn nesting() {
+        let value = parse(r#"{"b": [1, {"c": null}, []], "a": {"d": true}}"#).unwrap();
+        let expected = JsonValue::Object(vec![
+            (
+                String::from("b"),
+                JsonValue::Array(vec![
+                    JsonValue::Number(1.0),
+                    JsonValue::Object(vec![(String::from("c"), JsonValue::Null)]),
+                    JsonValue::Array(vec![]),
+                ]),
+            ),
+            (
+                String::from("a"),
+                JsonValue::Object(vec![(String::from("d"), JsonValue::Bool(true))]),
+            ),
+        ]);
+        assert_eq!(value, expected);
+
+        let deep = "[".repeat(MAX_DEPTH + 1) + &"]".repeat(MAX_DEPTH + 1);
+        assert!(parse(&deep).is_ok());
+        let deeper = "[".repeat(MAX_DEPTH + 2) + &"]".repeat(MAX_DEPTH + 2);
+        assert_error(&deeper, "nesting too deep");
+    }
+
+    #[test]
+    fn error_positions() {
+        let e = error("{\n  \"a\": tru\n}");
+        assert_eq!((e.line, e.column), (2, 11));
+        assert!(e.message.contains("expected `true`"));
+
+        let e = error("{\"a\": 1,\n \"a\": 2}");
+        assert_eq!((e.line, e.column), (2, 5));
+        assert!(e.message.contains("duplicate key `a`"));
+
+        let e = error("[1,\n 2\n 3]");
+        assert_eq!((e.line, e.column), (3, 3));
+        assert!(e.message.contains("expected `,` or `]`"));
+
+        let e = error("{\"a\" 1}");
+        assert_eq!((e.line, e.column), (1, 6));
+        assert_eq!(e.message, "expected `:`, found `1`");
+        assert_eq!(e.to_string(), "1:6: expected `:`, found `1`");
+
+        let e = error("");
+        assert_eq!((e.line, e.column), (1, 1));
+        assert!(e.message.contains("end of input"));
+    }
+}
@@ -0,0 +1,19 @@
+pub use self::delusion::{Delusion, DrawItem};
+pub use self::shader::{FragmentContext, FrameUniforms, ShaderPayload};
+pub use self::shadow::ShadowMap;
+pub use self::tile::RenderTile;
+
+pub mod graph;
+pub mod graphics;
+pub mod headless;
+pub mod light;
+pub mod post;
+pub mod primitives;
+pub mod scene;
+pub mod shader;
+pub mod transform;
+
+mod delusion;
+mod json;
+mod shadow;
+mod tile;
@@ -0,0 +1,223 @@
+use nalgebra::{Matrix4, Vector3};
+
+use crate::graphics;
+use crate::transform::*;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 点光源与聚光灯默认的距离衰减系数
+pub static DEFAULT_ATTENUATION: Vector3<f32> = Vector3::new(1.0, 0.22, 0.2);
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 平行光的direction为指向光源的方向, 聚光灯的direction为光锥轴向(从光源指向外)
+// attenuation为距离衰减 1 / (c + l * d + q * d^2) 的系数(c, l, q)
+// inner/outer为聚光灯内外锥的半角(角度制), 两者之间光强平滑过渡
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LightKind {
+    Directional {
+        direction: Vector3<f32>,
+    },
+    Point {
+        position: Vector3<f32>,
+        attenuation: Vector3<f32>,
+    },
+    Spot {
+        position: Vector3<f32>,
+        direction: Vector3<f32>,
+        inner: f32,
+        outer: f32,
+        attenuation: Vector3<f32>,
+    },
+}
+
+// 世界空间中的光源, color取0~1
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+}
+
+impl Light {
+    pub fn new(kind: LightKind, color: &Vector3<f32>, intensity: f32) -> Light {
+        Light {
+            kind,
+            color: *color,
+            intensity,
+        }
+    }
+
+    pub fn directional(direction: &Vector3<f32>) -> Light {
+        let kind = LightKind::Directional {
+            direction: direction.normalize(),
+        };
+        Light::new(kind, &Vector3::new(1.0, 1.0, 1.0), 1.0)
+    }
+
+    pub fn point(position: &Vector3<f32>) -> Light {
+        let kind = LightKind::Point {
+            position: *position,
+            attenuation: DEFAULT_ATTENUATION,
+        };
+        Light::new(kind, &Vector3::new(1.0, 1.0, 1.0), 1.0)
+    }
+
+    pub fn spot(
+        position: &Vector3<f32>,
+        direction: &Vector3<f32>,
+        inner: f32,
+        outer: f32,
+    ) -> Light {
+        let kind = LightKind::Spot {
+            position: *position,
+            direction: direction.normalize(),
+            inner,
+            outer,
+            attenuation: DEFAULT_ATTENUATION,
+        };
+        Light::new(kind, &Vector3::new(1.0, 1.0, 1.0), 1.0)
+    }
+
+    // 世界空间中一点p处指向光源的单位方向, 以及到达p的光强(颜色 * 强度 * 衰减)
+    pub fn incident(&self, p: &Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+        let radiance: Vector3<f32> = self.color * self.intensity;
+        match self.kind {
+            LightKind::Directional { direction } => (direction, radiance),
+            LightKind::Point {
+                position,
+                attenuation,
+            } => {
+                let (l, d) = to_light(&position, p);
+                (l, radiance * falloff(&attenuation, d))
+            }
+            LightKind::Spot {
+                position,
+                direction,
+                inner,
+                outer,
+                attenuation,
+            } => {
+                let (l, d) = to_light(&position, p);
+                let cos_inner = graphics::degree_to_radian(inner).cos();
+                let cos_outer = graphics::degree_to_radian(outer).cos();
+                let cone = match cos_inner - cos_outer {
+                    w if w > f32::EPSILON => ((-l).dot(&direction) - cos_outer) / w,
+                    _ => match (-l).dot(&direction) >= cos_outer {
+                        true => 1.0,
+                        false => 0.0,
+                    },
+                };
+                let cone = cone.clamp(0.0, 1.0);
+                let cone = cone * cone * (3.0 - 2.0 * cone);
+                (l, radiance * (falloff(&attenuation, d) * cone))
+            }
+        }
+    }
+
+    // 方向按w=0, 位置按w=1变换
+    pub fn transformed(&self, m: &Matrix4<f32>) -> Light {
+        let dir = |v: &Vector3<f32>| (m * vec3f_to_vec4f(v, 0.0)).xyz().normalize();
+        let pos = |v: &Vector3<f32>| (m * vec3f_to_vec4f(v, 1.0)).xyz();
+        let kind = match self.kind {
+            LightKind::Directional { direction } => LightKind::Directional {
+                direction: dir(&direction),
+            },
+            LightKind::Point {
+                position,
+                attenuation,
+            } => LightKind::Point {
+                position: pos(&position),
+                attenuation,
+            },
+            LightKind::Spot {
+                position,
+                direction,
+                inner,
+                outer,
+                attenuation,
+            } => LightKind::Spot {
+                position: pos(&position),
+                direction: dir(&direction),
+                inner,
+                outer,
+                attenuation,
+            },
+        };
+        Light { kind, ..*self }
+    }
+}
+
+impl Default for Light {
+    fn default() -> Light {
+        Light::directional(&Vector3::new(0.0, 1.0, 1.0))
+    }
+}
+
+fn to_light(position: &Vector3<f32>, p: &Vector3<f32>) -> (Vector3<f32>, f32) {
+    let v: Vector3<f32> = position - p;
+    let d = v.norm();
+    match d > f32::EPSILON {
+        true => (v / d, d),
+        false => (Vector3::new(0.0, 0.0, 1.0), 0.0),
+    }
+}
+
+#[inline]
+fn falloff(attenuation: &Vector3<f32>, d: f32) -> f32 {
+    1.0 / (attenuation.x + attenuation.y * d + attenuation.z * d * d).max(f32::EPSILON)
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 绘制时使用的光源, 第一个平行光投射阴影, 阴影只衰减它的贡献
+#[derive(Clone)]
+pub struct LightSet {
+    lights: Vec<Light>,
+    caster: Option<usize>,
+}
+
+impl LightSet {
+    pub fn new(lights: &[Light]) -> LightSet {
+        LightSet {
+            lights: lights.to_vec(),
+            caster: lights
+                .iter()
+                .position(|light| matches!(light.kind, LightKind::Directional { .. })),
+        }
+    }
+
+    #[inline]
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    // 阴影预渲染的视线方向
+    pub fn shadow_direction(&self) -> Option<Vector3<f32>> {
+        self.caster.map(|i| match self.lights[i].kind {
+            LightKind::Directional { direction } => direction,
+            _ => unreachable!(),
+        })
+    }
+
+    // 依次给出p处各光源的入射方向与光强, shadow为投射阴影的光源在p处的衰减系数
+    pub fn incident<'a>(
+        &'a self,
+        p: &'a Vector3<f32>,
+        shadow: f32,
+    ) -> impl Iterator<Item = (Vector3<f32>, Vector3<f32>)> + 'a {
+        self.lights().iter().enumerate().map(move |(i, light)| {
+            let (l, radiance) = light.incident(p);
+            match Some(i) == self.caster {
+                true => (l, radiance * shadow),
+                false => (l, radiance),
+            }
+        })
+    }
+}
+
+impl Default for LightSet {
+    fn default() -> LightSet {
+        LightSet::new(&[])
+    }
+}
@@ -0,0 +1,1080 @@
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+    fs, io,
+};
+
+use nalgebra::{Matrix3, Matrix4, Vector3, Vector4};
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 内置后处理效果的名称, 场景未列出的效果以默认参数加入后处理链并保持关闭
+pub static BUILTIN_EFFECTS: [&str; 5] = ["fxaa", "bloom", "vignette", "sharpen", "ssao"];
+
+static LUMA: Vector3<f32> = Vector3::new(0.299, 0.587, 0.114);
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// Hdr阶段在色调映射之前, 颜色为线性值(1.0为白, 可超出)
+// Display阶段在色调映射之后, 颜色为sRGB编码的显示颜色(0~1)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PostStage {
+    Hdr,
+    Display,
+}
+
+// 后处理可访问的整帧颜色与深度, 坐标原点在左上角, y轴向下
+// 深度越大越近, 未绘制的像素为f32::MIN
+// m_screen为观察空间到屏幕空间的变换(视口 * 投影), 用于由深度重建观察空间位置
+pub struct PostFrame<'a> {
+    width: usize,
+    height: usize,
+    color: &'a mut [Vector3<f32>],
+    depth: &'a [f32],
+    m_screen: Matrix4<f32>,
+    m_screen_inverse: Matrix4<f32>,
+}
+
+impl<'a> PostFrame<'a> {
+    // color按行自上而下, depth按行自下而上存储, 与RenderTile一致
+    pub fn new(
+        width: usize,
+        height: usize,
+        color: &'a mut [Vector3<f32>],
+        depth: &'a [f32],
+        m_screen: &Matrix4<f32>,
+    ) -> PostFrame<'a> {
+        PostFrame {
+            width,
+            height,
+            color,
+            depth,
+            m_screen: *m_screen,
+            m_screen_inverse: m_screen.try_inverse().unwrap_or_else(Matrix4::identity),
+        }
+    }
+
+    // 共享深度与变换, 颜色换为color, 供需要读取原始颜色的效果使用
+    pub fn with_color<'b>(&self, color: &'b mut [Vector3<f32>]) -> PostFrame<'b>
+    where
+        'a: 'b,
+    {
+        PostFrame {
+            width: self.width,
+            height: self.height,
+            color,
+            depth: self.depth,
+            m_screen: self.m_screen,
+            m_screen_inverse: self.m_screen_inverse,
+        }
+    }
+
+    // 越界坐标截断到边缘
+    #[inline]
+    pub fn color(&self, x: i64, y: i64) -> Vector3<f32> {
+        let x = x.clamp(0, self.width as i64 - 1) as usize;
+        let y = y.clamp(0, self.height as i64 - 1) as usize;
+        self.color[x + y * self.width]
+    }
+
+    #[inline]
+    pub fn set_color(&mut self, x: usize, y: usize, color: &Vector3<f32>) {
+        self.color[x + y * self.width] = *color;
+    }
+
+    #[inline]
+    pub fn depth(&self, x: usize, y: usize) -> f32 {
+        self.depth[x + (self.height - 1 - y) * self.width]
+    }
+
+    // 像素处可见表面在观察空间中的位置, 未绘制的像素返回None
+    // 光栅化写入的深度为 z / w + 0.5, 屏幕空间y轴向上
+    pub fn view_position(&self, x: usize, y: usize) -> Option<Vector3<f32>> {
+        let depth = self.depth(x, y);
+        if depth == f32::MIN {
+            return None;
+        }
+        let screen = Vector4::new(x as f32, (self.height - 1 - y) as f32, depth - 0.5, 1.0);
+        let v: Vector4<f32> = self.m_screen_inverse * screen;
+        Some(v.xyz() / v.w)
+    }
+
+    // 观察空间中的点投影为像素坐标(y轴向下)与深度
+    pub fn project(&self, p: &Vector3<f32>) -> Vector3<f32> {
+        let s: Vector4<f32> = self.m_screen * Vector4::new(p.x, p.y, p.z, 1.0);
+        Vector3::new(
+            s.x / s.w,
+            (self.height - 1) as f32 - s.y / s.w,
+            s.z / s.w + 0.5,
+        )
+    }
+
+    // 双线性采样, 像素中心位于整数坐标
+    pub fn sample(&self, x: f32, y: f32) -> Vector3<f32> {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (ix, iy) = (x0 as i64, y0 as i64);
+        let top = self.color(ix, iy) * (1.0 - tx) + self.color(ix + 1, iy) * tx;
+        let bottom = self.color(ix, iy + 1) * (1.0 - tx) + self.color(ix + 1, iy + 1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+
+    #[inline]
+    pub fn colors(&self) -> &[Vector3<f32>] {
+        self.color
+    }
+    #[inline]
+    pub fn colors_mut(&mut self) -> &mut [Vector3<f32>] {
+        self.color
+    }
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+pub trait PostPass {
+    fn name(&self) -> &str;
+    fn stage(&self) -> PostStage;
+    fn apply(&self, frame: &mut PostFrame);
+}
+
+struct PostEntry {
+    pass: Box<dyn PostPass>,
+    enabled: bool,
+}
+
+// 后处理链, 每个阶段只执行该阶段已启用的效果, 同一阶段内按加入顺序执行
+// 所有Hdr效果总在Display效果之前, 与加入的先后无关
+pub struct PostChain {
+    passes: Vec<PostEntry>,
+}
+
+impl PostChain {
+    pub fn new() -> PostChain {
+        PostChain { passes: Vec::new() }
+    }
+
+    pub fn push(&mut self, pass: Box<dyn PostPass>, enabled: bool) {
+        self.passes.push(PostEntry { pass, enabled });
+    }
+
+    pub fn run(&self, stage: PostStage, frame: &mut PostFrame) {
+        for entry in &self.passes {
+            if entry.enabled && entry.pass.stage() == stage {
+                entry.pass.apply(frame);
+            }
+        }
+    }
+
+    // 同名效果全部切换, 链中没有该效果时返回false
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let mut found = false;
+        for entry in self.passes.iter_mut().filter(|e| e.pass.name() == name) {
+            entry.enabled = enabled;
+            found = true;
+        }
+        found
+    }
+
+    // 返回切换后的状态, 链中没有该效果时返回None
+    pub fn toggle(&mut self, name: &str) -> Option<bool> {
+        let enabled = !self.is_enabled(name);
+        match self.set_enabled(name, enabled) {
+            true => Some(enabled),
+            false => None,
+        }
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.passes
+            .iter()
+            .any(|e| e.enabled && e.pass.name() == name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.passes.iter().any(|e| e.pass.name() == name)
+    }
+}
+
+impl Default for PostChain {
+    fn default() -> PostChain {
+        PostChain::new()
+    }
+}
+
+impl Display for PostChain {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let names: Vec<&str> = self
+            .passes
+            .iter()
+            .filter(|e| e.enabled)
+            .map(|e| e.pass.name())
+            .collect();
+        match names.is_empty() {
+            true => write!(f, "Off"),
+            false => write!(f, "{}", names.join("+")),
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 场景中描述的后处理效果, 调色只记录LUT文件路径, 构建时再载入
+#[derive(Clone, PartialEq, Debug)]
+pub enum PostEffect {
+    Fxaa(Fxaa),
+    Bloom(Bloom),
+    Vignette(Vignette),
+    Sharpen(Sharpen),
+    Ssao(Ssao),
+    ColorGrade(String),
+}
+
+impl PostEffect {
+    // 以默认参数构造内置效果, 调色需要LUT文件, 不能只凭名称构造
+    pub fn from_name(name: &str) -> Option<PostEffect> {
+        match name {
+            "fxaa" => Some(PostEffect::Fxaa(Fxaa::default())),
+            "bloom" => Some(PostEffect::Bloom(Bloom::default())),
+            "vignette" => Some(PostEffect::Vignette(Vignette::default())),
+            "sharpen" => Some(PostEffect::Sharpen(Sharpen::default())),
+            "ssao" => Some(PostEffect::Ssao(Ssao::default())),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PostEffect::Fxaa(_) => "fxaa",
+            PostEffect::Bloom(_) => "bloom",
+            PostEffect::Vignette(_) => "vignette",
+            PostEffect::Sharpen(_) => "sharpen",
+            PostEffect::Ssao(_) => "ssao",
+            PostEffect::ColorGrade(_) => "lut",
+        }
+    }
+
+    pub fn stage(&self) -> PostStage {
+        match self {
+            PostEffect::Bloom(_) | PostEffect::Ssao(_) => PostStage::Hdr,
+            _ => PostStage::Display,
+        }
+    }
+
+    pub fn build(&self) -> Result<Box<dyn PostPass>, LutError> {
+        Ok(match self {
+            PostEffect::Fxaa(pass) => Box::new(*pass),
+            PostEffect::Bloom(pass) => Box::new(*pass),
+            PostEffect::Vignette(pass) => Box::new(*pass),
+            PostEffect::Sharpen(pass) => Box::new(*pass),
+            PostEffect::Ssao(pass) => Box::new(*pass),
+            PostEffect::ColorGrade(file) => Box::new(ColorGrade::new(Lut3d::load(file)?)),
+        })
+    }
+}
+
+// 列表中排在Display效果之后的第一个Hdr效果的位置
+// 后处理链按阶段执行, 这样的列表无法按书写顺序执行
+pub fn misordered(effects: &[PostEffect]) -> Option<usize> {
+    let display = effects
+        .iter()
+        .position(|e| e.stage() == PostStage::Display)?;
+    effects[display..]
+        .iter()
+        .position(|e| e.stage() == PostStage::Hdr)
+        .map(|i| display + i)
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+static FXAA_REDUCE_MIN: f32 = 1.0 / 128.0;
+static FXAA_REDUCE_MUL: f32 = 1.0 / 8.0;
+static FXAA_SPAN_MAX: f32 = 8.0;
+
+// 快速近似抗锯齿: 由邻域亮度梯度估计边缘方向, 沿边缘方向采样混合
+// 邻域亮度差低于 max(edge_threshold_min, edge_threshold * 最大亮度) 的像素不处理
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Fxaa {
+    pub edge_threshold: f32,
+    pub edge_threshold_min: f32,
+}
+
+impl Fxaa {
+    pub fn new(edge_threshold: f32, edge_threshold_min: f32) -> Fxaa {
+        Fxaa {
+            edge_threshold,
+            edge_threshold_min,
+        }
+    }
+}
+
+impl Default for Fxaa {
+    fn default() -> Fxaa {
+        Fxaa::new(0.125, 0.0312)
+    }
+}
+
+impl PostPass for Fxaa {
+    fn name(&self) -> &str {
+        "fxaa"
+    }
+    fn stage(&self) -> PostStage {
+        PostStage::Display
+    }
+    fn apply(&self, frame: &mut PostFrame) {
+        let mut source: Vec<Vector3<f32>> = frame.colors().to_vec();
+        let src = frame.with_color(&mut source);
+        let luma = |x: i64, y: i64| src.color(x, y).dot(&LUMA);
+        for y in 0..frame.height() {
+            for x in 0..frame.width() {
+                let (ix, iy) = (x as i64, y as i64);
+                let (nw, ne) = (luma(ix - 1, iy - 1), luma(ix + 1, iy - 1));
+                let (sw, se) = (luma(ix - 1, iy + 1), luma(ix + 1, iy + 1));
+                let m = luma(ix, iy);
+                let lmin = m.min(nw.min(ne)).min(sw.min(se));
+                let lmax = m.max(nw.max(ne)).max(sw.max(se));
+                if lmax - lmin < self.edge_threshold_min.max(lmax * self.edge_threshold) {
+                    continue;
+                }
+
+                // 边缘方向垂直于亮度梯度
+                let mut dir_x = -((nw + ne) - (sw + se));
+                let mut dir_y = (nw + sw) - (ne + se);
+                let reduce = ((nw + ne + sw + se) * 0.25 * FXAA_REDUCE_MUL).max(FXAA_REDUCE_MIN);
+                let rcp = 1.0 / (dir_x.abs().min(dir_y.abs()) + reduce);
+                dir_x = (dir_x * rcp).clamp(-FXAA_SPAN_MAX, FXAA_SPAN_MAX);
+                dir_y = (dir_y * rcp).clamp(-FXAA_SPAN_MAX, FXAA_SPAN_MAX);
+
+                let (fx, fy) = (x as f32, y as f32);
+                let along = |t: f32| src.sample(fx + dir_x * t, fy + dir_y * t);
+                let a = (along(1.0 / 3.0 - 0.5) + along(2.0 / 3.0 - 0.5)) * 0.5;
+                let b = a * 0.5 + (along(-0.5) + along(0.5)) * 0.25;
+                let lb = b.dot(&LUMA);
+                match lb < lmin || lb > lmax {
+                    true => frame.set_color(x, y, &a),
+                    false => frame.set_color(x, y, &b),
+                }
+            }
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 泛光: 亮度超过threshold的部分经可分离高斯模糊后按intensity叠加, radius为模糊半径(像素)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Bloom {
+    pub threshold: f32,
+    pub intensity: f32,
+    pub radius: usize,
+}
+
+impl Bloom {
+    pub fn new(threshold: f32, intensity: f32, radius: usize) -> Bloom {
+        Bloom {
+            threshold,
+            intensity,
+            radius,
+        }
+    }
+}
+
+impl Default for Bloom {
+    fn default() -> Bloom {
+        Bloom::new(1.0, 0.5, 8)
+    }
+}
+
+impl PostPass for Bloom {
+    fn name(&self) -> &str {
+        "bloom"
+    }
+    fn stage(&self) -> PostStage {
+        PostStage::Hdr
+    }
+    fn apply(&self, frame: &mut PostFrame) {
+        let (w, h) = (frame.width(), frame.height());
+        let bright: Vec<Vector3<f32>> = frame
+            .colors()
+            .iter()
+            .map(|c| {
+                let l = c.dot(&LUMA);
+                match l > self.threshold {
+                    true => c * ((l - self.threshold) / l),
+                    false => Vector3::repeat(0.0),
+                }
+            })
+            .collect();
+
+        let r = self.radius as i64;
+        let sigma = (self.radius as f32 / 2.0).max(0.5);
+        let kernel: Vec<f32> = (-r..=r)
+            .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let sum: f32 = kernel.iter().sum();
+        let kernel: Vec<f32> = kernel.iter().map(|k| k / sum).collect();
+        let blur = |src: &[Vector3<f32>], dx: i64, dy: i64| -> Vec<Vector3<f32>> {
+            let mut dst: Vec<Vector3<f32>> = vec![Vector3::repeat(0.0); w * h];
+            for y in 0..h as i64 {
+                for x in 0..w as i64 {
+                    let mut acc: Vector3<f32> = Vector3::repeat(0.0);
+                    for (k, i) in kernel.iter().zip(-r..=r) {
+                        let sx = (x + i * dx).clamp(0, w as i64 - 1);
+                        let sy = (y + i * dy).clamp(0, h as i64 - 1);
+                        acc += src[(sx + sy * w as i64) as usize] * *k;
+                    }
+                    dst[(x + y * w as i64) as usize] = acc;
+                }
+            }
+            dst
+        };
+        let blurred = blur(&blur(&bright, 1, 0), 0, 1);
+        for (c, b) in frame.colors_mut().iter_mut().zip(blurred.iter()) {
+            *c += b * self.intensity;
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 暗角: 到画面中心的距离(中心为0, 角落为1)超过radius后逐渐压暗, 角落处乘以 1 - strength
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Vignette {
+    pub strength: f32,
+    pub radius: f32,
+}
+
+impl Vignette {
+    pub fn new(strength: f32, radius: f32) -> Vignette {
+        Vignette { strength, radius }
+    }
+}
+
+impl Default for Vignette {
+    fn default() -> Vignette {
+        Vignette::new(0.5, 0.5)
+    }
+}
+
+impl PostPass for Vignette {
+    fn name(&self) -> &str {
+        "vignette"
+    }
+    fn stage(&self) -> PostStage {
+        PostStage::Display
+    }
+    fn apply(&self, frame: &mut PostFrame) {
+        let (w, h) = (frame.width(), frame.height());
+        let width = (1.0 - self.radius).max(f32::EPSILON);
+        for y in 0..h {
+            for x in 0..w {
+                let u = (x as f32 + 0.5) / w as f32 - 0.5;
+                let v = (y as f32 + 0.5) / h as f32 - 0.5;
+                let d = (u * u + v * v).sqrt() * std::f32::consts::SQRT_2;
+                let t = ((d - self.radius) / width).clamp(0.0, 1.0);
+                let factor = 1.0 - self.strength * t * t * (3.0 - 2.0 * t);
+                let color = frame.color(x as i64, y as i64) * factor;
+                frame.set_color(x, y, &color);
+            }
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 锐化: 加上amount倍的拉普拉斯高频分量
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Sharpen {
+    pub amount: f32,
+}
+
+impl Sharpen {
+    pub fn new(amount: f32) -> Sharpen {
+        Sharpen { amount }
+    }
+}
+
+impl Default for Sharpen {
+    fn default() -> Sharpen {
+        Sharpen::new(0.3)
+    }
+}
+
+impl PostPass for Sharpen {
+    fn name(&self) -> &str {
+        "sharpen"
+    }
+    fn stage(&self) -> PostStage {
+        PostStage::Display
+    }
+    fn apply(&self, frame: &mut PostFrame) {
+        let mut source: Vec<Vector3<f32>> = frame.colors().to_vec();
+        let src = frame.with_color(&mut source);
+        for y in 0..frame.height() {
+            for x in 0..frame.width() {
+                let (ix, iy) = (x as i64, y as i64);
+                let c = src.color(ix, iy);
+                let edge = c * 4.0
+                    - src.color(ix - 1, iy)
+                    - src.color(ix + 1, iy)
+                    - src.color(ix, iy - 1)
+                    - src.color(ix, iy + 1);
+                let color = (c + edge * self.amount).map(|c| c.clamp(0.0, 1.0));
+                frame.set_color(x, y, &color);
+            }
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 整数哈希到[0, 1], 采样核与噪声每帧相同, 画面不闪烁
+fn hash(i: u32) -> f32 {
+    let mut x = i.wrapping_mul(0x9e37_79b9);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x85eb_ca6b);
+    x ^= x >> 13;
+    x = x.wrapping_mul(0xc2b2_ae35);
+    x ^= x >> 16;
+    x as f32 / u32::MAX as f32
+}
+
+// 屏幕空间环境光遮蔽: 由深度与投影的逆重建观察空间位置与法线, 在法线半球内取samples个采样点
+// 采样点投影回屏幕后被更近的表面挡住即计为遮蔽, radius为观察空间中的采样半径
+// 遮蔽经blur像素半径的盒式模糊消除噪声后, 在HDR颜色上按strength压暗
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Ssao {
+    pub radius: f32,
+    pub bias: f32,
+    pub samples: usize,
+    pub strength: f32,
+    pub blur: usize,
+}
+
+impl Ssao {
+    pub fn new(radius: f32, bias: f32, samples: usize, strength: f32, blur: usize) -> Ssao {
+        Ssao {
+            radius,
+            bias,
+            samples: samples.max(1),
+            strength,
+            blur,
+        }
+    }
+
+    // 半球内的采样核, 靠近中心的采样点更密
+    fn kernel(&self) -> Vec<Vector3<f32>> {
+        (0..self.samples as u32)
+            .map(|i| {
+                let v = Vector3::new(
+                    hash(4 * i) * 2.0 - 1.0,
+                    hash(4 * i + 1) * 2.0 - 1.0,
+                    hash(4 * i + 2),
+                );
+                let v = v
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_else(|| Vector3::new(0.0, 0.0, 1.0));
+                let t = i as f32 / self.samples as f32;
+                v * hash(4 * i + 3) * (0.1 + 0.9 * t * t)
+            })
+            .collect()
+    }
+
+    // 每个像素的遮蔽系数, 1为无遮蔽
+    fn occlusion(&self, frame: &PostFrame) -> Vec<f32> {
+        let (w, h) = (frame.width(), frame.height());
+        let positions: Vec<Option<Vector3<f32>>> = (0..h)
+            .flat_map(|y| (0..w).map(move |x| (x, y)))
+            .map(|(x, y)| frame.view_position(x, y))
+            .collect();
+        let at = |x: i64, y: i64| match x >= 0 && y >= 0 && x < w as i64 && y < h as i64 {
+            true => positions[x as usize + y as usize * w],
+            false => None,
+        };
+        let kernel = self.kernel();
+
+        let mut ao: Vec<f32> = vec![1.0; w * h];
+        for y in 0..h as i64 {
+            for x in 0..w as i64 {
+                let p = match at(x, y) {
+                    Some(p) => p,
+                    None => continue,
+                };
+                // 法线由相邻像素的位置差重建, 取深度变化较小的一侧以免跨越物体边缘
+                let side = |a: Option<Vector3<f32>>, b: Option<Vector3<f32>>| match (a, b) {
+                    (Some(a), Some(b)) => match (a - p).z.abs() < (p - b).z.abs() {
+                        true => Some(a - p),
+                        false => Some(p - b),
+                    },
+                    (Some(a), None) => Some(a - p),
+                    (None, Some(b)) => Some(p - b),
+                    (None, None) => None,
+                };
+                // 屏幕向右与向上的切向量叉积朝向相机
+                let normal = match (
+                    side(at(x + 1, y), at(x - 1, y)),
+                    side(at(x, y - 1), at(x, y + 1)),
+                ) {
+                    (Some(dx), Some(dy)) => dx.cross(&dy).try_normalize(f32::EPSILON),
+                    _ => None,
+                };
+                let n = match normal {
+                    Some(n) => n,
+                    None => continue,
+                };
+
+                // 4x4平铺的随机旋转, 交由模糊消除
+                let angle =
+                    hash(0x5bd1 + (x % 4 + 4 * (y % 4)) as u32) * std::f32::consts::PI * 2.0;
+                let r = Vector3::new(angle.cos(), angle.sin(), 0.0);
+                let t = (r - n * n.dot(&r))
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_else(|| n.cross(&Vector3::new(1.0, 0.0, 0.0)).normalize());
+                let tbn = Matrix3::from_columns(&[t, n.cross(&t), n]);
+
+                let mut occlusion: f32 = 0.0;
+                for k in &kernel {
+                    let s: Vector3<f32> = p + tbn * k * self.radius;
+                    let screen = frame.project(&s);
+                    let q = match at(screen.x.round() as i64, screen.y.round() as i64) {
+                        Some(q) => q,
+                        None => continue,
+                    };
+                    // 观察空间z轴指向相机, 遮挡物离采样中心过远时减弱其贡献
+                    if q.z >= s.z + self.bias {
+                        let distance = (p.z - q.z).abs().max(f32::EPSILON);
+                        let range = (self.radius / distance).min(1.0);
+                        occlusion += range * range * (3.0 - 2.0 * range);
+                    }
+                }
+                ao[x as usize + y as usize * w] = 1.0 - occlusion / kernel.len() as f32;
+            }
+        }
+
+        let r = self.blur as i64;
+        let mut blurred: Vec<f32> = vec![1.0; w * h];
+        for y in 0..h as i64 {
+            for x in 0..w as i64 {
+                if at(x, y).is_none() {
+                    continue;
+                }
+                let (mut sum, mut count) = (0.0, 0);
+                for sy in y - r..=y + r {
+                    for sx in x - r..=x + r {
+                        if at(sx, sy).is_some() {
+                            sum += ao[sx as usize + sy as usize * w];
+                            count += 1;
+                        }
+                    }
+                }
+                blurred[x as usize + y as usize * w] = sum / count as f32;
+            }
+        }
+        blurred
+    }
+}
+
+impl Default for Ssao {
+    fn default() -> Ssao {
+        Ssao::new(0.3, 0.02, 16, 1.0, 2)
+    }
+}
+
+impl PostPass for Ssao {
+    fn name(&self) -> &str {
+        "ssao"
+    }
+    fn stage(&self) -> PostStage {
+        PostStage::Hdr
+    }
+    fn apply(&self, frame: &mut PostFrame) {
+        let ao = self.occlusion(frame);
+        for (c, a) in frame.colors_mut().iter_mut().zip(ao.iter()) {
+            *c *= 1.0 - self.strength * (1.0 - a);
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+pub enum LutError {
+    Io {
+        file: String,
+        source: io::Error,
+    },
+    Parse {
+        file: String,
+        line: usize,
+        message: String,
+    },
+}
+
+impl Display for LutError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            LutError::Io { file, source } => write!(f, "{}: {}", file, source),
+            LutError::Parse {
+                file,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", file, line, message),
+        }
+    }
+}
+
+impl Error for LutError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LutError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// 三维颜色查找表, 表项按r最快, b最慢的顺序存储(与.cube文件一致)
+// 输入颜色先由[domain_min, domain_max]映射到[0, 1]
+#[derive(Clone, PartialEq, Debug)]
+pub struct Lut3d {
+    size: usize,
+    table: Vec<Vector3<f32>>,
+    domain_min: Vector3<f32>,
+    domain_max: Vector3<f32>,
+}
+
+impl Lut3d {
+    // 不改变颜色的查找表
+    pub fn identity(size: usize) -> Lut3d {
+        let size = size.max(2);
+        let step = 1.0 / (size - 1) as f32;
+        let mut table: Vec<Vector3<f32>> = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    table.push(Vector3::new(r as f32, g as f32, b as f32) * step);
+                }
+            }
+        }
+        Lut3d {
+            size,
+            table,
+            domain_min: Vector3::repeat(0.0),
+            domain_max: Vector3::repeat(1.0),
+        }
+    }
+
+    // 读取Adobe/Resolve的.cube文件, 只支持三维查找表
+    pub fn load(file: &str) -> Result<Lut3d, LutError> {
+        let text = fs::read_to_string(file).map_err(|source| LutError::Io {
+            file: file.to_string(),
+            source,
+        })?;
+        let error = |line: usize, message: String| LutError::Parse {
+            file: file.to_string(),
+            line,
+            message,
+        };
+        let floats = |line: usize, args: &[&str]| -> Result<Vector3<f32>, LutError> {
+            let c: Vec<f32> = args
+                .iter()
+                .map(|x| x.parse::<f32>())
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|_| error(line, String::from("expected numbers")))?;
+            match c.len() == 3 && c.iter().all(|x| x.is_finite()) {
+                true => Ok(Vector3::new(c[0], c[1], c[2])),
+                false => Err(error(
+                    line,
+                    format!("expected 3 numbers, found {}", c.len()),
+                )),
+            }
+        };
+
+        let mut size: Option<usize> = None;
+        let mut table: Vec<Vector3<f32>> = Vec::new();
+        let (mut domain_min, mut domain_max) = (Vector3::repeat(0.0), Vector3::repeat(1.0));
+        for (iline, line) in text.lines().enumerate() {
+            let iline = iline + 1;
+            let tmp: Vec<&str> = line.split_whitespace().collect();
+            let key = match tmp.first() {
+                Some(key) if !key.starts_with('#') => *key,
+                _ => continue,
+            };
+            match key {
+                "TITLE" => (),
+                "LUT_3D_SIZE" => {
+                    let n = tmp
+                        .get(1)
+                        .and_then(|n| n.parse::<usize>().ok())
+                        .filter(|&n| (2..=256).contains(&n))
+                        .ok_or_else(|| error(iline, String::from("expected a size in 2..=256")))?;
+                    size = Some(n);
+                }
+                "LUT_1D_SIZE" => {
+                    return Err(error(iline, String::from("1D LUTs are not supported")))
+                }
+                "DOMAIN_MIN" => domain_min = floats(iline, &tmp[1..])?,
+                "DOMAIN_MAX" => domain_max = floats(iline, &tmp[1..])?,
+                _ => {
+                    if size.is_none() {
+                        return Err(error(iline, String::from("data before LUT_3D_SIZE")));
+                    }
+                    table.push(floats(iline, &tmp)?);
+                }
+            }
+        }
+
+        // 整个文件的错误记在最后一行
+        let last = text.lines().count();
+        let size = size.ok_or_else(|| error(last, String::from("missing LUT_3D_SIZE")))?;
+        if table.len() != size * size * size {
+            return Err(error(
+                last,
+                format!(
+                    "expected {} entries, found {}",
+                    size * size * size,
+                    table.len()
+                ),
+            ));
+        }
+        if (0..3).any(|i| domain_max[i] <= domain_min[i]) {
+            return Err(error(
+                last,
+                String::from("DOMAIN_MAX must exceed DOMAIN_MIN"),
+            ));
+        }
+        Ok(Lut3d {
+            size,
+            table,
+            domain_min,
+            domain_max,
+        })
+    }
+
+    // 三线性插值查表
+    pub fn apply(&self, color: &Vector3<f32>) -> Vector3<f32> {
+        let n = self.size;
+        let p: Vector3<f32> = (color - self.domain_min)
+            .component_div(&(self.domain_max - self.domain_min))
+            .map(|c| c.clamp(0.0, 1.0) * (n - 1) as f32);
+        let lo: Vector3<usize> = p.map(|c| (c.floor() as usize).min(n - 2));
+        let t: Vector3<f32> = p - lo.map(|c| c as f32);
+        let at = |r: usize, g: usize, b: usize| self.table[r + g * n + b * n * n];
+        let lerp = |a: Vector3<f32>, b: Vector3<f32>, t: f32| a * (1.0 - t) + b * t;
+        let (r, g, b) = (lo.x, lo.y, lo.z);
+        let c00 = lerp(at(r, g, b), at(r + 1, g, b), t.x);
+        let c10 = lerp(at(r, g + 1, b), at(r + 1, g + 1, b), t.x);
+        let c01 = lerp(at(r, g, b + 1), at(r + 1, g, b + 1), t.x);
+        let c11 = lerp(at(r, g + 1, b + 1), at(r + 1, g + 1, b + 1), t.x);
+        lerp(lerp(c00, c10, t.y), lerp(c01, c11, t.y), t.z)
+    }
+
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+// 调色: 以sRGB编码的显示颜色查表
+#[derive(Clone)]
+pub struct ColorGrade {
+    lut: Lut3d,
+}
+
+impl ColorGrade {
+    pub fn new(lut: Lut3d) -> ColorGrade {
+        ColorGrade { lut }
+    }
+
+    #[inline]
+    pub fn lut(&self) -> &Lut3d {
+        &self.lut
+    }
+}
+
+impl PostPass for ColorGrade {
+    fn name(&self) -> &str {
+        "lut"
+    }
+    fn stage(&self) -> PostStage {
+        PostStage::Display
+    }
+    fn apply(&self, frame: &mut PostFrame) {
+        for c in frame.colors_mut() {
+            *c = self.lut.apply(c);
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 写入临时文件后载入
+    fn load(name: &str, text: &str) -> Result<Lut3d, LutError> {
+        let path =
+            std::env::temp_dir().join(format!("delusion_{}_{}.cube", std::process::id(), name));
+        fs::write(&path, text).unwrap();
+        let lut = Lut3d::load(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        lut
+    }
+
+    fn parse_error(name: &str, text: &str) -> (usize, String) {
+        match load(name, text) {
+            Err(LutError::Parse { line, message, .. }) => (line, message),
+            other => panic!("{:?}", other.map(|lut| lut.size())),
+        }
+    }
+
+    fn identity_cube(header: &str) -> String {
+        let mut text = format!("TITLE \"identity\"\n{}LUT_3D_SIZE 2\n", header);
+        for b in 0..2 {
+            for g in 0..2 {
+                for r in 0..2 {
+                    text += &format!("{} {} {}\n", r, g, b);
+                }
+            }
+        }
+        text
+    }
+
+    const COLORS: [[f32; 3]; 5] = [
+        [0.0, 0.0, 0.0],
+        [1.0, 1.0, 1.0],
+        [0.25, 0.5, 0.75],
+        [0.9, 0.1, 0.33],
+        [0.5, 0.0, 1.0],
+    ];
+
+    // 观察空间中过原点的平面 n·p = 0 的深度, 按屏幕行自下而上存储, 与光栅化写入的一致
+    fn plane_depth(m_screen: &Matrix4<f32>, size: usize, n: &Vector3<f32>) -> Vec<f32> {
+        let m = m_screen.try_inverse().unwrap();
+        let b: Vector4<f32> = m.column(2).into();
+        (0..size * size)
+            .map(|i| {
+                let a: Vector4<f32> =
+                    m * Vector4::new((i % size) as f32, (i / size) as f32, 0.0, 1.0);
+                -n.dot(&a.xyz()) / n.dot(&b.xyz()) + 0.5
+            })
+            .collect()
+    }
+
+    fn ssao_occlusion(m_screen: &Matrix4<f32>, size: usize, depth: &[f32]) -> Vec<f32> {
+        let mut color = vec![Vector3::new(1.0, 1.0, 1.0); size * size];
+        let frame = PostFrame::new(size, size, &mut color, depth, m_screen);
+        Ssao::default().occlusion(&frame)
+    }
+
+    #[test]
+    fn ssao_flat_and_corner() {
+        // 相机位于观察空间z = 3处看向原点
+        let size = 64;
+        let m_screen = crate::graphics::calc_m_viewport(size, size, 1.0)
+            * crate::graphics::calc_m_projection(-1.0 / 3.0);
+        let centre = size / 2 + size / 2 * size;
+
+        // 正对相机的平面没有遮蔽
+        let flat = plane_depth(&m_screen, size, &Vector3::new(0.0, 0.0, 1.0));
+        let ao = ssao_occlusion(&m_screen, size, &flat);
+        for y in 8..size - 8 {
+            for x in 8..size - 8 {
+                let ao = ao[x + y * size];
+                assert!(ao > 0.99, "({}, {}) {}", x, y, ao);
+            }
+        }
+
+        // 张角90度的凹槽 z = |x|, 可见表面为两平面中较近的一个, 槽底被两侧遮蔽
+        let left = plane_depth(&m_screen, size, &Vector3::new(-1.0, 0.0, 1.0));
+        let right = plane_depth(&m_screen, size, &Vector3::new(1.0, 0.0, 1.0));
+        let corner: Vec<f32> = left
+            .iter()
+            .zip(right.iter())
+            .map(|(l, r)| l.max(*r))
+            .collect();
+        let ao = ssao_occlusion(&m_screen, size, &corner);
+        assert!(ao[centre] < 0.95, "{}", ao[centre]);
+        // 离槽底超过采样半径的斜面不受遮蔽
+        for x in [8, size - 8].iter() {
+            let ao = ao[x + size / 2 * size];
+            assert!(ao > 0.99, "{}", ao);
+        }
+    }
+
+    #[test]
+    fn identity_round_trips() {
+        let lut = Lut3d::identity(17);
+        for c in COLORS.iter() {
+            let c = Vector3::new(c[0], c[1], c[2]);
+            assert!((lut.apply(&c) - c).norm() < 1e-5, "{}", c);
+        }
+        // 超出定义域的颜色截断到边界
+        let c = lut.apply(&Vector3::new(-1.0, 2.0, 0.5));
+        assert!((c - Vector3::new(0.0, 1.0, 0.5)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn identity_cube_file() {
+        let lut = load("identity", &identity_cube("# comment\n\n")).unwrap();
+        assert_eq!(lut, Lut3d::identity(2));
+        for c in COLORS.iter() {
+            let c = Vector3::new(c[0], c[1], c[2]);
+            assert!((lut.apply(&c) - c).norm() < 1e-5, "{}", c);
+        }
+
+        let lut = load("domain", &identity_cube("DOMAIN_MAX 2 2 2\n")).unwrap();
+        let c = lut.apply(&Vector3::new(1.0, 0.5, 2.0));
+        assert!((c - Vector3::new(0.5, 0.25, 1.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn wrong_entry_count() {
+        let text = identity_cube("");
+        let short = text.trim_end().rsplit_once('\n').unwrap().0;
+        let (line, message) = parse_error("short", short);
+        assert_eq!(line, 9);
+        assert_eq!(message, "expected 8 entries, found 7");
+
+        let long = text + "1 1 1\n";
+        let (_, message) = parse_error("long", &long);
+        assert_eq!(message, "expected 8 entries, found 9");
+    }
+
+    #[test]
+    fn data_before_size() {
+        let (line, message) = parse_error("before", "TITLE \"x\"\n0 0 0\nLUT_3D_SIZE 2\n");
+        assert_eq!(line, 2);
+        assert_eq!(message, "data before LUT_3D_SIZE");
+    }
+
+    #[test]
+    fn empty_domain() {
+        let (_, message) = parse_error(
+            "domain",
+            &identity_cube("DOMAIN_MIN 0 0.5 0\nDOMAIN_MAX 1 0.5 1\n"),
+        );
+        assert_eq!(message, "DOMAIN_MAX must exceed DOMAIN_MIN");
+    }
+
+    #[test]
+    fn malformed_lines() {
+        let (line, message) = parse_error("size", "LUT_3D_SIZE 1\n");
+        assert_eq!((line, message.as_str()), (1, "expected a size in 2..=256"));
+        let (line, message) = parse_error("floats", "LUT_3D_SIZE 2\n0 0\n");
+        assert_eq!((line, message.as_str()), (2, "expected 3 numbers, found 2"));
+        let (_, message) = parse_error("1d", "LUT_1D_SIZE 16\n");
+        assert_eq!(message, "1D LUTs are not supported");
+        assert!(matches!(
+            Lut3d::load("/nonexistent/delusion.cube"),
+            Err(LutError::Io { .. })
+        ));
+    }
+}
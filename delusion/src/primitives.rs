@@ -4,7 +4,6 @@ use nalgebra::{Vector2, Vector3};
 pub struct Triangle {
     v: Vector3<Vector3<f32>>,
     v_i32: Vector3<Vector3<i32>>,
-    color: Vector3<Vector3<u8>>,
     tex_coords: Vector3<Vector2<i32>>,
     normals: Vector3<Vector3<f32>>,
 }
@@ -14,7 +13,6 @@ impl Triangle {
         Triangle {
             v: Default::default(),
             v_i32: Default::default(),
-            color: Default::default(),
             tex_coords: Default::default(),
             normals: Default::default(),
         }
@@ -87,3 +85,9 @@ impl Triangle {
         self.normals[2]
     }
 }
+
+impl Default for Triangle {
+    fn default() -> Triangle {
+        Triangle::new()
+    }
+}
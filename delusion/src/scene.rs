@@ -0,0 +1,1034 @@
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+    fs, io,
+    path::Path,
+};
+
+use nalgebra::{Matrix4, Vector3};
+
+use objcracker::{Filter, NormalMode, NormalOptions, NormalWeight, ObjError, Objcracker, Sampler};
+
+use crate::delusion::Delusion;
+use crate::graph::{SceneGraph, Transform};
+use crate::graphics;
+use crate::graphics::{MsaaOptions, ShadowOptions, TileOptions, ToneMap, ToneOptions};
+use crate::json;
+use crate::json::JsonValue;
+use crate::light::{Light, LightKind};
+use crate::post;
+use crate::post::{
+    Bloom, Fxaa, LutError, PostChain, PostEffect, Sharpen, Ssao, Vignette, BUILTIN_EFFECTS,
+};
+use crate::shader::ShaderKind;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+static VIEWPORT_FACTOR: f32 = 0.75;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io {
+        file: String,
+        source: io::Error,
+    },
+    Syntax {
+        file: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    InvalidField {
+        file: String,
+        field: String,
+        message: String,
+    },
+}
+
+impl SceneError {
+    pub fn file(&self) -> &str {
+        match self {
+            SceneError::Io { file, .. }
+            | SceneError::Syntax { file, .. }
+            | SceneError::InvalidField { file, .. } => file,
+        }
+    }
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            SceneError::Io { file, source } => write!(f, "{}: {}", file, source),
+            SceneError::Syntax {
+                file,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", file, line, column, message),
+            SceneError::InvalidField {
+                file,
+                field,
+                message,
+            } => write!(f, "{}: `{}`: {}", file, field, message),
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// 字段校验失败时先记录字段路径, 由Scene::parse补上文件名
+struct Invalid {
+    field: String,
+    message: String,
+}
+
+fn invalid(field: &str, message: String) -> Invalid {
+    Invalid {
+        field: field.to_string(),
+        message,
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// path为不含.obj扩展名的模型路径前缀, 为空的节点只用于组织子节点
+// 子节点的变换相对于父节点, shader为空时使用渲染设置中的默认着色器
+// normals为模型缺少vn时生成法线的方式
+#[derive(Clone)]
+pub struct SceneModel {
+    pub path: Option<String>,
+    pub transform: Transform,
+    pub shader: Option<ShaderKind>,
+    pub normals: NormalOptions,
+    pub children: Vec<SceneModel>,
+}
+
+impl SceneModel {
+    pub fn new(path: &str) -> SceneModel {
+        SceneModel {
+            path: Some(path.to_string()),
+            transform: Transform::identity(),
+            shader: None,
+            normals: NormalOptions::default(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn group() -> SceneModel {
+        SceneModel {
+            path: None,
+            transform: Transform::identity(),
+            shader: None,
+            normals: NormalOptions::default(),
+            children: Vec::new(),
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy)]
+pub struct SceneCamera {
+    pub eye: Vector3<f32>,
+    pub target: Vector3<f32>,
+    pub up: Vector3<f32>,
+}
+
+impl SceneCamera {
+    pub fn new(eye: &Vector3<f32>, target: &Vector3<f32>, up: &Vector3<f32>) -> SceneCamera {
+        SceneCamera {
+            eye: *eye,
+            target: *target,
+            up: *up,
+        }
+    }
+
+    // 视线不能为零向量, 也不能与上方向平行
+    pub fn is_valid(&self) -> bool {
+        let view = self.eye - self.target;
+        view.norm() > f32::EPSILON
+            && self.up.norm() > f32::EPSILON
+            && view.normalize().cross(&self.up.normalize()).norm() > 1e-4
+    }
+
+    #[inline]
+    pub fn m_camera(&self) -> Matrix4<f32> {
+        graphics::calc_m_camera(&self.eye, &self.target, &self.up)
+    }
+    #[inline]
+    pub fn m_projection(&self) -> Matrix4<f32> {
+        graphics::calc_m_projection(-1.0 / (self.eye - self.target).norm())
+    }
+}
+
+impl Default for SceneCamera {
+    fn default() -> SceneCamera {
+        SceneCamera::new(
+            &Vector3::new(0.0, 1.0, 3.0),
+            &Vector3::new(0.0, 0.0, 0.0),
+            &Vector3::new(0.0, 1.0, 0.0),
+        )
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// background取0~255, shadow为空表示关闭阴影, post为按顺序启用的后处理
+#[derive(Clone)]
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub msaa: MsaaOptions,
+    pub background: Vector3<f32>,
+    pub shadow: Option<ShadowOptions>,
+    pub filter: Filter,
+    pub tiling: bool,
+    pub shader: ShaderKind,
+    pub tone: ToneOptions,
+    pub post: Vec<PostEffect>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> RenderSettings {
+        RenderSettings {
+            width: 800,
+            height: 800,
+            msaa: MsaaOptions::Disable,
+            background: Vector3::new(255.0, 255.0, 255.0),
+            shadow: Some(ShadowOptions::default()),
+            filter: Filter::Trilinear,
+            tiling: true,
+            shader: ShaderKind::NmSpec,
+            tone: ToneOptions::default(),
+            post: Vec::new(),
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 场景描述: 模型树及各节点的变换与着色器, 光源, 相机和渲染设置
+#[derive(Clone)]
+pub struct Scene {
+    pub models: Vec<SceneModel>,
+    pub lights: Vec<Light>,
+    pub camera: SceneCamera,
+    pub settings: RenderSettings,
+}
+
+impl Scene {
+    pub fn new() -> Scene {
+        Scene {
+            models: Vec::new(),
+            lights: vec![Light::directional(&Vector3::new(0.0, 1.0, 1.0))],
+            camera: SceneCamera::default(),
+            settings: RenderSettings::default(),
+        }
+    }
+
+    // 模型路径相对于场景文件所在目录
+    pub fn load(file: &str) -> Result<Scene, SceneError> {
+        let text = fs::read_to_string(file).map_err(|source| SceneError::Io {
+            file: file.to_string(),
+            source,
+        })?;
+        let mut scene = Scene::parse(&text, file)?;
+        if let Some(dir) = Path::new(file).parent() {
+            resolve_paths(&mut scene.models, dir);
+            resolve_post_paths(&mut scene.settings.post, dir);
+        }
+        Ok(scene)
+    }
+
+    // file仅用于错误信息
+    pub fn parse(text: &str, file: &str) -> Result<Scene, SceneError> {
+        let root = json::parse(text).map_err(|e| SceneError::Syntax {
+            file: file.to_string(),
+            line: e.line,
+            column: e.column,
+            message: e.message,
+        })?;
+        read_scene(&root).map_err(|e| SceneError::InvalidField {
+            file: file.to_string(),
+            field: e.field,
+            message: e.message,
+        })
+    }
+
+    // 加载所有模型并构建场景图, 加载失败的模型所在节点保留变换但不绘制
+    pub fn instantiate(&self) -> (SceneInstance, Vec<ObjError>) {
+        let mut graph = SceneGraph::new();
+        let root = graph.add_node(None, Transform::identity());
+        let mut instance = SceneInstance {
+            graph,
+            root,
+            models: Vec::new(),
+            shaders: Vec::new(),
+        };
+        let mut errors: Vec<ObjError> = Vec::new();
+        add_nodes(
+            &self.models,
+            root,
+            self.settings.shader,
+            &mut instance,
+            &mut errors,
+        );
+        instance.graph.update_world();
+        (instance, errors)
+    }
+
+    // 按场景的画布尺寸创建渲染器, 并应用相机与渲染设置
+    pub fn renderer(&self) -> Delusion {
+        let settings = &self.settings;
+        let mut d = Delusion::new(settings.width, settings.height);
+        d.set_camera(self.camera.m_camera());
+        d.set_eye(&self.camera.eye);
+        d.set_projection(self.camera.m_projection());
+        d.set_viewport(graphics::calc_m_viewport(
+            settings.width,
+            settings.height,
+            VIEWPORT_FACTOR,
+        ));
+        d.enable_msaa(settings.msaa);
+        d.set_sampler(Sampler::new(settings.filter));
+        d.set_tone_mapping(settings.tone);
+        if let Some(options) = settings.shadow {
+            d.enable_shadow(options);
+        }
+        if settings.tiling {
+            d.enable_tiling(TileOptions::auto());
+        }
+        d
+    }
+
+    // 场景中列出的后处理按顺序启用, 未列出的内置效果以默认参数追加在后并关闭, 供运行时切换
+    // LUT载入失败的调色跳过
+    pub fn post_chain(&self) -> (PostChain, Vec<LutError>) {
+        let mut chain = PostChain::new();
+        let mut errors: Vec<LutError> = Vec::new();
+        for effect in &self.settings.post {
+            match effect.build() {
+                Ok(pass) => chain.push(pass, true),
+                Err(e) => errors.push(e),
+            }
+        }
+        for name in BUILTIN_EFFECTS.iter() {
+            if chain.contains(name) {
+                continue;
+            }
+            if let Some(Ok(pass)) = PostEffect::from_name(name).map(|e| e.build()) {
+                chain.push(pass, false);
+            }
+        }
+        (chain, errors)
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Scene {
+        Scene::new()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 场景图的根节点为单位变换, 场景中的顶层模型都是它的子节点
+// shaders[i]为models[i]所用的着色器, 与场景图节点中的着色器下标对应
+pub struct SceneInstance {
+    pub graph: SceneGraph,
+    pub root: usize,
+    pub models: Vec<Objcracker>,
+    pub shaders: Vec<ShaderKind>,
+}
+
+fn add_nodes(
+    models: &[SceneModel],
+    parent: usize,
+    shader: ShaderKind,
+    instance: &mut SceneInstance,
+    errors: &mut Vec<ObjError>,
+) {
+    for entry in models {
+        let id = instance.graph.add_node(Some(parent), entry.transform);
+        if let Some(path) = &entry.path {
+            let mut model = Objcracker::new(path);
+            model.set_normal_options(entry.normals);
+            match model.interpret() {
+                Ok(()) => {
+                    let imodel = instance.models.len();
+                    instance.graph.attach_model(id, imodel, imodel);
+                    instance.models.push(model);
+                    instance.shaders.push(entry.shader.unwrap_or(shader));
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        add_nodes(&entry.children, id, shader, instance, errors);
+    }
+}
+
+fn resolve_paths(models: &mut [SceneModel], dir: &Path) {
+    for model in models {
+        if let Some(path) = &model.path {
+            model.path = Some(dir.join(path).to_string_lossy().into_owned());
+        }
+        resolve_paths(&mut model.children, dir);
+    }
+}
+
+fn resolve_post_paths(effects: &mut [PostEffect], dir: &Path) {
+    for effect in effects {
+        if let PostEffect::ColorGrade(file) = effect {
+            *file = dir.join(&file).to_string_lossy().into_owned();
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+fn read_scene(root: &JsonValue) -> Result<Scene, Invalid> {
+    let mut scene = Scene::new();
+    let entries = object(root, "scene", &["models", "lights", "camera", "settings"])?;
+    for (key, value) in entries {
+        match key.as_str() {
+            "models" => {
+                scene.models = array(value, key)?
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| read_model(v, &format!("models[{}]", i)))
+                    .collect::<Result<Vec<SceneModel>, Invalid>>()?
+            }
+            "lights" => {
+                scene.lights = array(value, key)?
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| read_light(v, &format!("lights[{}]", i)))
+                    .collect::<Result<Vec<Light>, Invalid>>()?;
+                if scene.lights.is_empty() {
+                    return Err(invalid(key, String::from("at least one light is required")));
+                }
+            }
+            "camera" => scene.camera = read_camera(value, key)?,
+            _ => scene.settings = read_settings(value, key)?,
+        }
+    }
+    if scene.models.is_empty() {
+        return Err(invalid(
+            "models",
+            String::from("at least one model is required"),
+        ));
+    }
+    Ok(scene)
+}
+
+fn read_model(value: &JsonValue, field: &str) -> Result<SceneModel, Invalid> {
+    let keys = [
+        "path",
+        "translation",
+        "rotation",
+        "scale",
+        "shader",
+        "normals",
+        "children",
+    ];
+    let entries = object(value, field, &keys)?;
+    let mut model = SceneModel::group();
+    for (key, value) in entries {
+        let field = format!("{}.{}", field, key);
+        match key.as_str() {
+            "path" => {
+                let path = string(value, &field)?;
+                model.path = Some(path.strip_suffix(".obj").unwrap_or(path).to_string());
+            }
+            "translation" => model.transform.translation = vec3(value, &field)?,
+            "rotation" => {
+                model.transform.rotation = Transform::euler_degrees(&vec3(value, &field)?)
+            }
+            "scale" => {
+                let scale = match value {
+                    JsonValue::Number(_) => Vector3::repeat(number(value, &field)?),
+                    _ => vec3(value, &field)?,
+                };
+                if scale.iter().any(|s| *s == 0.0) {
+                    return Err(invalid(&field, String::from("scale must be non-zero")));
+                }
+                model.transform.scale = scale;
+            }
+            "shader" => model.shader = Some(shader(value, &field)?),
+            "normals" => model.normals = read_normals(value, &field)?,
+            _ => {
+                model.children = array(value, &field)?
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| read_model(v, &format!("{}[{}]", field, i)))
+                    .collect::<Result<Vec<SceneModel>, Invalid>>()?
+            }
+        }
+    }
+    if model.path.is_none() && model.children.is_empty() {
+        return Err(invalid(
+            field,
+            String::from("expected `path` or `children`"),
+        ));
+    }
+    Ok(model)
+}
+
+// type缺省为平行光; 平行光需要direction, 点光源需要position, 聚光灯两者都需要
+fn read_light(value: &JsonValue, field: &str) -> Result<Light, Invalid> {
+    let keys = [
+        "type",
+        "direction",
+        "position",
+        "color",
+        "intensity",
+        "attenuation",
+        "inner",
+        "outer",
+    ];
+    let entries = object(value, field, &keys)?;
+    let get = |key: &str| entries.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+    let sub = |key: &str| format!("{}.{}", field, key);
+    let require = |key: &str| get(key).ok_or_else(|| invalid(field, format!("missing `{}`", key)));
+    let unused = |keys: &[&str], kind: &str| match keys.iter().find(|key| get(key).is_some()) {
+        Some(key) => Err(invalid(&sub(key), format!("not used by {} lights", kind))),
+        None => Ok(()),
+    };
+    let direction = |value: &JsonValue| {
+        let direction = vec3(value, &sub("direction"))?;
+        match direction.norm() > f32::EPSILON {
+            true => Ok(direction),
+            false => Err(invalid(
+                &sub("direction"),
+                String::from("direction must be non-zero"),
+            )),
+        }
+    };
+    let kind = match get("type") {
+        Some(value) => string(value, &sub("type"))?,
+        None => "directional",
+    };
+    let mut light = match kind {
+        "directional" => {
+            unused(&["position", "attenuation", "inner", "outer"], kind)?;
+            Light::directional(&direction(require("direction")?)?)
+        }
+        "point" => {
+            unused(&["direction", "inner", "outer"], kind)?;
+            Light::point(&vec3(require("position")?, &sub("position"))?)
+        }
+        "spot" => {
+            let angle = |key: &str, default: f32| match get(key) {
+                Some(value) => number(value, &sub(key)),
+                None => Ok(default),
+            };
+            let (inner, outer) = (angle("inner", 20.0)?, angle("outer", 30.0)?);
+            if !(0.0 <= inner && inner <= outer && outer < 90.0) {
+                return Err(invalid(
+                    field,
+                    String::from("expected 0 <= inner <= outer < 90"),
+                ));
+            }
+            let position = vec3(require("position")?, &sub("position"))?;
+            Light::spot(&position, &direction(require("direction")?)?, inner, outer)
+        }
+        _ => {
+            return Err(invalid(
+                &sub("type"),
+                String::from("expected directional, point or spot"),
+            ))
+        }
+    };
+    for (key, value) in entries {
+        let field = sub(key);
+        match key.as_str() {
+            "color" => {
+                light.color = vec3(value, &field)?;
+                if light.color.iter().any(|c| !(0.0..=1.0).contains(c)) {
+                    return Err(invalid(&field, String::from("components must be in 0..=1")));
+                }
+            }
+            "intensity" => {
+                light.intensity = number(value, &field)?;
+                if light.intensity < 0.0 {
+                    return Err(invalid(&field, String::from("intensity must be >= 0")));
+                }
+            }
+            "attenuation" => {
+                let k = vec3(value, &field)?;
+                if k.iter().any(|c| *c < 0.0) || k.sum() <= 0.0 {
+                    return Err(invalid(
+                        &field,
+                        String::from("components must be >= 0 and not all zero"),
+                    ));
+                }
+                match &mut light.kind {
+                    LightKind::Point { attenuation, .. } | LightKind::Spot { attenuation, .. } => {
+                        *attenuation = k
+                    }
+                    LightKind::Directional { .. } => (),
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(light)
+}
+
+fn read_camera(value: &JsonValue, field: &str) -> Result<SceneCamera, Invalid> {
+    let mut camera = SceneCamera::default();
+    for (key, value) in object(value, field, &["eye", "target", "up"])? {
+        let field = format!("{}.{}", field, key);
+        match key.as_str() {
+            "eye" => camera.eye = vec3(value, &field)?,
+            "target" => camera.target = vec3(value, &field)?,
+            _ => camera.up = vec3(value, &field)?,
+        }
+    }
+    if !camera.is_valid() {
+        return Err(invalid(
+            field,
+            String::from("eye must differ from target and the view must not be parallel to up"),
+        ));
+    }
+    Ok(camera)
+}
+
+fn read_settings(value: &JsonValue, field: &str) -> Result<RenderSettings, Invalid> {
+    let mut settings = RenderSettings::default();
+    let keys = [
+        "width",
+        "height",
+        "msaa",
+        "background",
+        "shadow",
+        "filter",
+        "tiling",
+        "shader",
+        "tonemap",
+        "exposure",
+        "post",
+    ];
+    for (key, value) in object(value, field, &keys)? {
+        let field = format!("{}.{}", field, key);
+        match key.as_str() {
+            "width" => settings.width = extent(value, &field)?,
+            "height" => settings.height = extent(value, &field)?,
+            "msaa" => {
+                settings.msaa = MsaaOptions::from_samples(integer(value, &field)?)
+                    .ok_or_else(|| invalid(&field, String::from("expected 0, 2, 4, 8 or 16")))?
+            }
+            "background" => {
+                settings.background = vec3(value, &field)?;
+                if settings
+                    .background
+                    .iter()
+                    .any(|c| !(0.0..=255.0).contains(c))
+                {
+                    return Err(invalid(
+                        &field,
+                        String::from("components must be in 0..=255"),
+                    ));
+                }
+            }
+            "shadow" => settings.shadow = read_shadow(value, &field)?,
+            "filter" => {
+                settings.filter = match string(value, &field)? {
+                    "nearest" => Filter::Nearest,
+                    "bilinear" => Filter::Bilinear,
+                    "trilinear" => Filter::Trilinear,
+                    _ => {
+                        return Err(invalid(
+                            &field,
+                            String::from("expected nearest, bilinear or trilinear"),
+                        ))
+                    }
+                }
+            }
+            "tiling" => settings.tiling = boolean(value, &field)?,
+            "shader" => settings.shader = shader(value, &field)?,
+            "tonemap" => {
+                settings.tone.operator =
+                    ToneMap::from_name(string(value, &field)?).ok_or_else(|| {
+                        invalid(
+                            &field,
+                            String::from("expected none, reinhard, aces or exposure"),
+                        )
+                    })?
+            }
+            "post" => {
+                settings.post = array(value, &field)?
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| read_post(v, &format!("{}[{}]", field, i)))
+                    .collect::<Result<Vec<PostEffect>, Invalid>>()?;
+                if let Some(i) = post::misordered(&settings.post) {
+                    let message = format!(
+                        "{} runs before tone mapping and must be listed before display passes",
+                        settings.post[i].name()
+                    );
+                    return Err(invalid(&format!("{}[{}]", field, i), message));
+                }
+            }
+            _ => {
+                settings.tone.exposure = number(value, &field)?;
+                if settings.tone.exposure <= 0.0 {
+                    return Err(invalid(&field, String::from("must be > 0")));
+                }
+            }
+        }
+    }
+    Ok(settings)
+}
+
+// 字符串以默认参数启用效果, 对象由type选择效果, 其余字段覆盖默认参数
+// 调色只能用对象给出LUT文件
+fn read_post(value: &JsonValue, field: &str) -> Result<PostEffect, Invalid> {
+    let expected = || String::from("expected fxaa, bloom, vignette, sharpen, ssao or lut");
+    if let JsonValue::String(name) = value {
+        return PostEffect::from_name(name).ok_or_else(|| match name.as_str() {
+            "lut" => invalid(field, String::from("lut needs an object with `file`")),
+            _ => invalid(field, expected()),
+        });
+    }
+    let keys = [
+        "type",
+        "edge_threshold",
+        "edge_threshold_min",
+        "threshold",
+        "intensity",
+        "radius",
+        "strength",
+        "amount",
+        "bias",
+        "samples",
+        "blur",
+        "file",
+    ];
+    let entries = object(value, field, &keys)?;
+    let get = |key: &str| entries.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+    let sub = |key: &str| format!("{}.{}", field, key);
+    let kind = match get("type") {
+        Some(value) => string(value, &sub("type"))?,
+        None => return Err(invalid(field, String::from("missing `type`"))),
+    };
+    let used: &[&str] = match kind {
+        "fxaa" => &["edge_threshold", "edge_threshold_min"],
+        "bloom" => &["threshold", "intensity", "radius"],
+        "vignette" => &["strength", "radius"],
+        "sharpen" => &["amount"],
+        "ssao" => &["radius", "bias", "samples", "strength", "blur"],
+        "lut" => &["file"],
+        _ => return Err(invalid(&sub("type"), expected())),
+    };
+    if let Some((key, _)) = entries
+        .iter()
+        .find(|(key, _)| key != "type" && !used.contains(&key.as_str()))
+    {
+        return Err(invalid(&sub(key), format!("not used by {} passes", kind)));
+    }
+    let param = |key: &str, default: f32| match get(key) {
+        Some(value) => match number(value, &sub(key))? {
+            x if x >= 0.0 => Ok(x),
+            _ => Err(invalid(&sub(key), String::from("must be >= 0"))),
+        },
+        None => Ok(default),
+    };
+    Ok(match kind {
+        "fxaa" => {
+            let d = Fxaa::default();
+            PostEffect::Fxaa(Fxaa::new(
+                param("edge_threshold", d.edge_threshold)?,
+                param("edge_threshold_min", d.edge_threshold_min)?,
+            ))
+        }
+        "bloom" => {
+            let d = Bloom::default();
+            let radius = match get("radius") {
+                Some(value) => integer(value, &sub("radius"))?,
+                None => d.radius,
+            };
+            PostEffect::Bloom(Bloom::new(
+                param("threshold", d.threshold)?,
+                param("intensity", d.intensity)?,
+                radius,
+            ))
+        }
+        "vignette" => {
+            let d = Vignette::default();
+            let radius = param("radius", d.radius)?;
+            if radius >= 1.0 {
+                return Err(invalid(&sub("radius"), String::from("must be < 1")));
+            }
+            PostEffect::Vignette(Vignette::new(param("strength", d.strength)?, radius))
+        }
+        "sharpen" => PostEffect::Sharpen(Sharpen::new(param("amount", Sharpen::default().amount)?)),
+        "ssao" => {
+            let d = Ssao::default();
+            let radius = param("radius", d.radius)?;
+            if radius <= 0.0 {
+                return Err(invalid(&sub("radius"), String::from("must be > 0")));
+            }
+            let strength = param("strength", d.strength)?;
+            if strength > 1.0 {
+                return Err(invalid(&sub("strength"), String::from("must be <= 1")));
+            }
+            let samples = match get("samples") {
+                Some(value) => extent(value, &sub("samples"))?,
+                None => d.samples,
+            };
+            let blur = match get("blur") {
+                Some(value) => integer(value, &sub("blur"))?,
+                None => d.blur,
+            };
+            PostEffect::Ssao(Ssao::new(
+                radius,
+                param("bias", d.bias)?,
+                samples,
+                strength,
+                blur,
+            ))
+        }
+        _ => match get("file") {
+            Some(value) => PostEffect::ColorGrade(string(value, &sub("file"))?.to_string()),
+            None => return Err(invalid(field, String::from("missing `file`"))),
+        },
+    })
+}
+
+// 布尔值开关阴影, 对象则在默认参数上覆盖指定字段
+fn read_shadow(value: &JsonValue, field: &str) -> Result<Option<ShadowOptions>, Invalid> {
+    if let JsonValue::Bool(enabled) = value {
+        return Ok(match enabled {
+            true => Some(ShadowOptions::default()),
+            false => None,
+        });
+    }
+    let mut options = ShadowOptions::default();
+    for (key, value) in object(value, field, &["size", "bias", "pcf", "extent"])? {
+        let field = format!("{}.{}", field, key);
+        match key.as_str() {
+            "size" => options.size = extent(value, &field)?,
+            "bias" => options.bias = number(value, &field)?,
+            "pcf" => options.pcf = integer(value, &field)?,
+            _ => options.extent = number(value, &field)?,
+        }
+    }
+    if options.bias < 0.0 || options.extent <= 0.0 {
+        return Err(invalid(
+            field,
+            String::from("bias must be >= 0 and extent must be > 0"),
+        ));
+    }
+    Ok(Some(options))
+}
+
+// 字符串只选择生成方式, 对象则在默认参数上覆盖指定字段
+fn read_normals(value: &JsonValue, field: &str) -> Result<NormalOptions, Invalid> {
+    let mut options = NormalOptions::default();
+    if let JsonValue::String(_) = value {
+        options.mode = normal_mode(value, field)?;
+        return Ok(options);
+    }
+    let keys = [
+        "mode",
+        "weight",
+        "smoothing_groups",
+        "crease_angle",
+        "force",
+    ];
+    for (key, value) in object(value, field, &keys)? {
+        let field = format!("{}.{}", field, key);
+        match key.as_str() {
+            "mode" => options.mode = normal_mode(value, &field)?,
+            "weight" => {
+                options.weight = match string(value, &field)? {
+                    "area" => NormalWeight::Area,
+                    "angle" => NormalWeight::Angle,
+                    _ => return Err(invalid(&field, String::from("expected area or angle"))),
+                }
+            }
+            "smoothing_groups" => options.smoothing_groups = boolean(value, &field)?,
+            "crease_angle" => {
+                let angle = number(value, &field)?;
+                if !(0.0..=180.0).contains(&angle) {
+                    return Err(invalid(&field, String::from("must be in 0..=180")));
+                }
+                options.crease_angle = Some(angle);
+            }
+            _ => options.force = boolean(value, &field)?,
+        }
+    }
+    Ok(options)
+}
+
+fn normal_mode(value: &JsonValue, field: &str) -> Result<NormalMode, Invalid> {
+    match string(value, field)? {
+        "flat" => Ok(NormalMode::Flat),
+        "smooth" => Ok(NormalMode::Smooth),
+        _ => Err(invalid(field, String::from("expected flat or smooth"))),
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 未知字段视为错误, 避免拼写错误被静默忽略
+fn object<'a>(
+    value: &'a JsonValue,
+    field: &str,
+    keys: &[&str],
+) -> Result<&'a [(String, JsonValue)], Invalid> {
+    match value {
+        JsonValue::Object(entries) => {
+            match entries
+                .iter()
+                .find(|(key, _)| !keys.contains(&key.as_str()))
+            {
+                Some((key, _)) => Err(invalid(field, format!("unknown field `{}`", key))),
+                None => Ok(entries),
+            }
+        }
+        _ => Err(mismatch(value, field, "object")),
+    }
+}
+
+fn array<'a>(value: &'a JsonValue, field: &str) -> Result<&'a [JsonValue], Invalid> {
+    match value {
+        JsonValue::Array(items) => Ok(items),
+        _ => Err(mismatch(value, field, "array")),
+    }
+}
+
+fn string<'a>(value: &'a JsonValue, field: &str) -> Result<&'a str, Invalid> {
+    match value {
+        JsonValue::String(s) => Ok(s),
+        _ => Err(mismatch(value, field, "string")),
+    }
+}
+
+fn boolean(value: &JsonValue, field: &str) -> Result<bool, Invalid> {
+    match value {
+        JsonValue::Bool(b) => Ok(*b),
+        _ => Err(mismatch(value, field, "boolean")),
+    }
+}
+
+fn number(value: &JsonValue, field: &str) -> Result<f32, Invalid> {
+    match value {
+        JsonValue::Number(n) if (*n as f32).is_finite() => Ok(*n as f32),
+        JsonValue::Number(_) => Err(invalid(field, String::from("number out of range"))),
+        _ => Err(mismatch(value, field, "number")),
+    }
+}
+
+fn integer(value: &JsonValue, field: &str) -> Result<usize, Invalid> {
+    match value {
+        JsonValue::Number(n) if *n >= 0.0 && n.fract() == 0.0 && *n <= u32::MAX as f64 => {
+            Ok(*n as usize)
+        }
+        JsonValue::Number(_) => Err(invalid(
+            field,
+            String::from("expected a non-negative integer"),
+        )),
+        _ => Err(mismatch(value, field, "integer")),
+    }
+}
+
+fn extent(value: &JsonValue, field: &str) -> Result<usize, Invalid> {
+    match integer(value, field)? {
+        0 => Err(invalid(field, String::from("must be positive"))),
+        n => Ok(n),
+    }
+}
+
+fn vec3(value: &JsonValue, field: &str) -> Result<Vector3<f32>, Invalid> {
+    let items = array(value, field)?;
+    if items.len() != 3 {
+        return Err(invalid(
+            field,
+            format!("expected 3 numbers, found {}", items.len()),
+        ));
+    }
+    Ok(Vector3::new(
+        number(&items[0], field)?,
+        number(&items[1], field)?,
+        number(&items[2], field)?,
+    ))
+}
+
+fn shader(value: &JsonValue, field: &str) -> Result<ShaderKind, Invalid> {
+    ShaderKind::from_name(string(value, field)?).ok_or_else(|| {
+        invalid(
+            field,
+            String::from(
+                "expected weird, model, gouraud, diff, nm, spec, nmspec, tbn, pbr or depth",
+            ),
+        )
+    })
+}
+
+fn mismatch(value: &JsonValue, field: &str, expected: &str) -> Invalid {
+    invalid(
+        field,
+        format!("expected {}, found {}", expected, value.kind()),
+    )
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Vector2, Vector4};
+
+    use super::*;
+    use crate::light::LightSet;
+
+    #[test]
+    fn target_at_viewport_centre() {
+        let scene = Scene::parse(
+            r#"{
+                "camera": { "eye": [2, 3, 5], "target": [1, -0.5, 2], "up": [0, 1, 0] },
+                "settings": { "width": 300, "height": 200 },
+                "models": [{ "path": "model" }]
+            }"#,
+            "test.json",
+        )
+        .unwrap();
+        let d = scene.renderer();
+        let project = |p: Vector3<f32>| {
+            let q: Vector4<f32> = d.transform() * Vector4::new(p.x, p.y, p.z, 1.0);
+            q.xy() / q.w
+        };
+        // 观察点与视线上的其他点都落在画布中心
+        let camera = scene.camera;
+        let centre = Vector2::new(150.0, 100.0);
+        for t in [0.0, 0.5, -2.0].iter() {
+            let p = camera.target + (camera.eye - camera.target) * *t;
+            let q = project(p);
+            assert!((q - centre).norm() < 1e-3, "{} -> {}", p, q);
+        }
+        // 相机坐标系中观察点位于原点
+        let target = scene.camera.m_camera() * Vector4::new(1.0, -0.5, 2.0, 1.0);
+        assert!(target.xyz().norm() < 1e-5);
+    }
+    #[test]
+    fn any_number_of_lights() {
+        let lights = [r#"{ "type": "point", "position": [0, 1, 0] }"#; 12].join(", ");
+        let text = format!(
+            r#"{{ "lights": [{}], "models": [{{ "path": "m" }}] }}"#,
+            lights
+        );
+        let scene = Scene::parse(&text, "test.json").unwrap();
+        assert_eq!(scene.lights.len(), 12);
+        assert_eq!(LightSet::new(&scene.lights).lights().len(), 12);
+    }
+}
@@ -0,0 +1,1033 @@
+use std::{
+    fmt::{Display, Error, Formatter},
+    sync::Arc,
+};
+
+use nalgebra::{Matrix2x3, Matrix3, Matrix4, Vector3, Vector4};
+
+use objcracker::{Material, Objcracker, Sampler, TexCoord};
+
+use crate::delusion::Delusion;
+use crate::graphics::{calc_m_inverse_transpose, Interpolation};
+use crate::light::LightSet;
+use crate::shadow::ShadowMap;
+use crate::transform::*;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 分块光栅化时需要为每个三角形保存着色器快照
+// 光源经Arc在快照间共享, 复制快照只复制varying
+pub trait ShaderClone {
+    fn clone_box(&self) -> Box<dyn ShaderPayload>;
+}
+
+impl<T: 'static + ShaderPayload + Clone> ShaderClone for T {
+    fn clone_box(&self) -> Box<dyn ShaderPayload> {
+        Box::new(self.clone())
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 完全处于阴影中时投射阴影的光源仍保留的光照比例
+static SHADOW_AMBIENT: f32 = 0.3;
+// Phong类着色器的恒定环境光, 与各光源的贡献相加后乘以漫反射颜色
+static PHONG_AMBIENT: f32 = 0.02;
+
+// 片元着色器可访问的资源: 三角形所属材质, 本帧的阴影贴图与纹理采样器
+// ddx/ddy为光栅化器由2x2像素块求得的插值权重屏幕空间偏导
+#[derive(Clone, Copy)]
+pub struct FragmentContext<'a> {
+    pub material: &'a Material,
+    pub shadow: Option<&'a ShadowMap>,
+    pub sampler: Sampler,
+    pub ddx: Vector3<f32>,
+    pub ddy: Vector3<f32>,
+}
+
+impl<'a> FragmentContext<'a> {
+    pub fn new(
+        material: &'a Material,
+        shadow: Option<&'a ShadowMap>,
+        sampler: Sampler,
+    ) -> FragmentContext<'a> {
+        FragmentContext {
+            material,
+            shadow,
+            sampler,
+            ddx: Vector3::repeat(0.0),
+            ddy: Vector3::repeat(0.0),
+        }
+    }
+
+    // uv为顶点uv的线性组合, 其偏导即为权重偏导的同一线性组合
+    #[inline]
+    pub fn tex_coord(&self, varying_uv: &Matrix2x3<f32>, weights: &Vector3<f32>) -> TexCoord {
+        TexCoord::new(
+            varying_uv * weights,
+            varying_uv * self.ddx,
+            varying_uv * self.ddy,
+        )
+    }
+
+    // 未开启阴影时恒为1
+    #[inline]
+    pub fn visibility(&self, p: &Vector3<f32>) -> f32 {
+        self.shadow.map_or(1.0, |shadow| shadow.visibility(p))
+    }
+
+    // 投射阴影的光源在p处的衰减系数, 由可见度在SHADOW_AMBIENT~1之间插值
+    #[inline]
+    pub fn shadow_factor(&self, p: &Vector3<f32>) -> f32 {
+        SHADOW_AMBIENT + (1.0 - SHADOW_AMBIENT) * self.visibility(p)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 绘制时传给着色器的uniform, 随绘制项的模型矩阵与当前相机而变化
+// lights为世界空间中的光源, 一次绘制的所有绘制项共享, eye为世界空间中的相机位置
+// *_it为对应矩阵的逆转置
+#[derive(Clone)]
+pub struct FrameUniforms {
+    pub lights: Arc<LightSet>,
+    pub eye: Vector3<f32>,
+    pub model: Matrix4<f32>,
+    pub model_it: Matrix4<f32>,
+    pub view: Matrix4<f32>,
+    pub projection: Matrix4<f32>,
+    pub viewport: Matrix4<f32>,
+    pub view_projection: Matrix4<f32>,
+    pub view_projection_it: Matrix4<f32>,
+}
+
+impl FrameUniforms {
+    pub fn new(render: &Delusion, lights: &Arc<LightSet>) -> FrameUniforms {
+        let view_projection = render.projection() * render.camera();
+        FrameUniforms {
+            lights: Arc::clone(lights),
+            eye: *render.eye(),
+            model: *render.model(),
+            model_it: *render.model_it(),
+            view: *render.camera(),
+            projection: *render.projection(),
+            viewport: *render.viewport(),
+            view_projection,
+            view_projection_it: calc_m_inverse_transpose(&view_projection),
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 模型空间法线经模型矩阵的逆转置变换到世界空间
+#[inline]
+fn world_normal(render: &Delusion, n: &Vector3<f32>) -> Vector3<f32> {
+    (render.model_it() * vec3f_to_vec4f(n, 0.0))
+        .xyz()
+        .normalize()
+}
+
+// 由插值得到的世界空间顶点法线n与切线t构造TBN基, 将切线空间法线贴图变换到世界空间
+// sign为插值得到的副切线符号, 只取其正负; 无法线贴图时直接返回n
+fn tangent_normal(
+    material: &Material,
+    tc: &TexCoord,
+    sampler: &Sampler,
+    n: &Vector3<f32>,
+    t: &Vector3<f32>,
+    sign: f32,
+) -> Vector3<f32> {
+    let n: Vector3<f32> = n.normalize();
+    match material.normal_map() {
+        Some(_) => {
+            // 插值后的切线需重新与法线正交化
+            let t: Vector3<f32> = (t - n * n.dot(t)).normalize();
+            let b: Vector3<f32> = match sign < 0.0 {
+                true => -n.cross(&t),
+                false => n.cross(&t),
+            };
+            let nm: Vector3<f32> = material.normal(tc, sampler);
+            (t * nm.x + b * nm.y + n * nm.z).normalize()
+        }
+        None => n,
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+pub trait ShaderPayload: ShaderClone + Send + Sync {
+    // 每个绘制项的顶点阶段开始前调用, 着色器从中更新自己的uniform
+    fn bind(&mut self, _uniforms: &FrameUniforms) {}
+    fn vertex(
+        &mut self,
+        iface: usize,
+        ivert: usize,
+        model: &Objcracker,
+        render: &Delusion,
+    ) -> Vector4<f32>;
+    fn fragment(
+        &self,
+        weights: &Vector3<f32>,
+        model: &Objcracker,
+        ctx: &FragmentContext,
+    ) -> Vector3<f32>;
+    // 片元着色器接收的权重默认经过透视校正
+    fn interpolation(&self) -> Interpolation {
+        Interpolation::Perspective
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone)]
+pub struct GouraudShader {
+    varying_intensity: Matrix3<f32>,
+    varying_uv: Matrix2x3<f32>,
+    uniform_lights: Arc<LightSet>,
+}
+
+impl GouraudShader {
+    pub fn new() -> GouraudShader {
+        GouraudShader {
+            varying_intensity: Default::default(),
+            varying_uv: Default::default(),
+            uniform_lights: Arc::new(LightSet::default()),
+        }
+    }
+}
+
+impl ShaderPayload for GouraudShader {
+    fn bind(&mut self, uniforms: &FrameUniforms) {
+        self.uniform_lights = Arc::clone(&uniforms.lights);
+    }
+    fn vertex(
+        &mut self,
+        iface: usize,
+        ivert: usize,
+        model: &Objcracker,
+        render: &Delusion,
+    ) -> Vector4<f32> {
+        self.varying_uv
+            .set_column(ivert, &model.calc_uv(iface, ivert));
+        let v: Vector4<f32> = vec3f_to_vec4f(&model.calc_vert(iface, ivert), 1.0);
+        let pos: Vector3<f32> = (render.model() * v).xyz();
+        let n: Vector3<f32> = world_normal(render, &model.calc_normal(iface, ivert));
+        let mut intensity: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
+        for (l, radiance) in self.uniform_lights.incident(&pos, 1.0) {
+            intensity += radiance * n.dot(&l).max(0.0);
+        }
+        self.varying_intensity.set_column(ivert, &intensity);
+        render.transform() * v
+    }
+    fn fragment(
+        &self,
+        weights: &Vector3<f32>,
+        _model: &Objcracker,
+        ctx: &FragmentContext,
+    ) -> Vector3<f32> {
+        let material = ctx.material;
+        let intensity: Vector3<f32> = self.varying_intensity * weights;
+        let tc: TexCoord = ctx.tex_coord(&self.varying_uv, weights);
+        material
+            .diffuse(&tc, &ctx.sampler)
+            .component_mul(&intensity)
+    }
+}
+
+impl Display for GouraudShader {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "Gouraud_Shader::with texture")
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone)]
+pub struct WeirdShader {
+    varying_intensity: Vector3<f32>,
+    uniform_lights: Arc<LightSet>,
+}
+
+impl WeirdShader {
+    pub fn new() -> WeirdShader {
+        WeirdShader {
+            varying_intensity: Default::default(),
+            uniform_lights: Arc::new(LightSet::default()),
+        }
+    }
+}
+
+impl ShaderPayload for WeirdShader {
+    fn bind(&mut self, uniforms: &FrameUniforms) {
+        self.uniform_lights = Arc::clone(&uniforms.lights);
+    }
+    fn vertex(
+        &mut self,
+        iface: usize,
+        ivert: usize,
+        model: &Objcracker,
+        render: &Delusion,
+    ) -> Vector4<f32> {
+        let v: Vector4<f32> = vec3f_to_vec4f(&model.calc_vert(iface, ivert), 1.0);
+        let pos: Vector3<f32> = (render.model() * v).xyz();
+        let n: Vector3<f32> = world_normal(render, &model.calc_normal(iface, ivert));
+        // 分档着色只看亮度, 取各光源最亮通道之和
+        self.varying_intensity[ivert] = self
+            .uniform_lights
+            .incident(&pos, 1.0)
+            .map(|(l, radiance)| radiance.max() * n.dot(&l).max(0.0))
+            .sum();
+        render.transform() * v
+    }
+    fn fragment(
+        &self,
+        weights: &Vector3<f32>,
+        _model: &Objcracker,
+        _ctx: &FragmentContext,
+    ) -> Vector3<f32> {
+        let mut intensity: f32 = self.varying_intensity.dot(weights);
+        match intensity {
+            x if x > 0.85 => intensity = 1.0,
+            x if x > 0.6 => intensity = 0.8,
+            x if x > 0.4 => intensity = 0.6,
+            x if x > 0.3 => intensity = 0.4,
+            x if x > 0.15 => intensity = 0.3,
+            _ => intensity = 0.0,
+        }
+        Vector3::new(79.0, 147.0, 184.0) * intensity
+    }
+}
+
+impl Display for WeirdShader {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "Weird_Shader::without texture")
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone)]
+pub struct PhongShaderNmSpec {
+    varying_pos: Matrix3<f32>,
+    varying_uv: Matrix2x3<f32>,
+    uniform_lights: Arc<LightSet>,
+    uniform_m: Matrix4<f32>,
+    uniform_mit: Matrix4<f32>,
+    uniform_model_it: Matrix4<f32>,
+}
+
+impl PhongShaderNmSpec {
+    pub fn new() -> PhongShaderNmSpec {
+        PhongShaderNmSpec {
+            varying_pos: Default::default(),
+            varying_uv: Default::default(),
+            uniform_lights: Arc::new(LightSet::default()),
+            uniform_m: Matrix4::<f32>::identity(),
+            uniform_mit: Matrix4::<f32>::identity(),
+            uniform_model_it: Matrix4::<f32>::identity(),
+        }
+    }
+}
+
+impl ShaderPayload for PhongShaderNmSpec {
+    fn bind(&mut self, uniforms: &FrameUniforms) {
+        self.uniform_lights = Arc::clone(&uniforms.lights);
+        self.uniform_m = uniforms.view_projection;
+        self.uniform_mit = uniforms.view_projection_it;
+        self.uniform_model_it = uniforms.model_it;
+    }
+    fn vertex(
+        &mut self,
+        iface: usize,
+        ivert: usize,
+        model: &Objcracker,
+        render: &Delusion,
+    ) -> Vector4<f32> {
+        let pos: Vector4<f32> =
+            render.model() * vec3f_to_vec4f(&model.calc_vert(iface, ivert), 1.0);
+        self.varying_pos.set_column(ivert, &pos.xyz());
+        self.varying_uv
+            .set_column(ivert, &model.calc_uv(iface, ivert));
+        let vt: Vector4<f32> =
+            render.transform() * vec3f_to_vec4f(&model.calc_vert(iface, ivert), 1.0);
+        vt
+    }
+    fn fragment(
+        &self,
+        weights: &Vector3<f32>,
+        _model: &Objcracker,
+        ctx: &FragmentContext,
+    ) -> Vector3<f32> {
+        let pos: Vector3<f32> = self.varying_pos * weights;
+        let shadow: f32 = ctx.shadow_factor(&pos);
+        let material = ctx.material;
+        let tc: TexCoord = ctx.tex_coord(&self.varying_uv, weights);
+        let nm: Vector3<f32> = material.normal(&tc, &ctx.sampler);
+        let nm: Vector3<f32> = (self.uniform_model_it * vec3f_to_vec4f(&nm, 0.0))
+            .xyz()
+            .normalize();
+        let n: Vector3<f32> = (self.uniform_mit * vec3f_to_vec4f(&nm, 1.0))
+            .xyz()
+            .normalize();
+        let ns: f32 = material.specular(&tc, &ctx.sampler);
+        let mut light: Vector3<f32> = Vector3::repeat(PHONG_AMBIENT);
+        for (l, radiance) in self.uniform_lights.incident(&pos, shadow) {
+            let l: Vector3<f32> = (self.uniform_m * vec3f_to_vec4f(&l, 1.0)).xyz().normalize();
+            let r: Vector3<f32> = (n * ((n.dot(&l)) * 2.0) - l).normalize();
+            let spec: f32 = 0f32.max(r.z).powf(ns);
+            let diff: f32 = n.dot(&l).max(0.0);
+            light += radiance.component_mul(&(Vector3::repeat(diff) + material.ks() * spec));
+        }
+        material.diffuse(&tc, &ctx.sampler).component_mul(&light)
+    }
+}
+
+impl Display for PhongShaderNmSpec {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "Phong_Shader::with normal/specular mapping")
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 切线空间法线贴图: 由顶点法线与模型生成的切线在每个片元上构造TBN基,
+// 贴图中的法线经TBN变换到世界空间后与光照计算, 模型变换与uv镜像都能正确处理
+#[derive(Clone)]
+pub struct PhongShaderTbn {
+    varying_pos: Matrix3<f32>,
+    varying_uv: Matrix2x3<f32>,
+    varying_normal: Matrix3<f32>,
+    varying_tangent: Matrix3<f32>,
+    varying_sign: Vector3<f32>,
+    uniform_lights: Arc<LightSet>,
+    uniform_view_dir: Vector3<f32>,
+}
+
+impl PhongShaderTbn {
+    pub fn new() -> PhongShaderTbn {
+        PhongShaderTbn {
+            varying_pos: Default::default(),
+            varying_uv: Default::default(),
+            varying_normal: Default::default(),
+            varying_tangent: Default::default(),
+            varying_sign: Vector3::repeat(1.0),
+            uniform_lights: Arc::new(LightSet::default()),
+            uniform_view_dir: Vector3::new(0.0, 0.0, 1.0),
+        }
+    }
+}
+
+impl ShaderPayload for PhongShaderTbn {
+    // 相机矩阵第三行为世界空间中指向观察者的方向
+    fn bind(&mut self, uniforms: &FrameUniforms) {
+        self.uniform_lights = Arc::clone(&uniforms.lights);
+        self.uniform_view_dir = Vector3::new(
+            uniforms.view[(2, 0)],
+            uniforms.view[(2, 1)],
+            uniforms.view[(2, 2)],
+        )
+        .normalize();
+    }
+    fn vertex(
+        &mut self,
+        iface: usize,
+        ivert: usize,
+        model: &Objcracker,
+        render: &Delusion,
+    ) -> Vector4<f32> {
+        let v: Vector4<f32> = vec3f_to_vec4f(&model.calc_vert(iface, ivert), 1.0);
+        self.varying_pos
+            .set_column(ivert, &(render.model() * v).xyz());
+        self.varying_uv
+            .set_column(ivert, &model.calc_uv(iface, ivert));
+        self.varying_normal.set_column(
+            ivert,
+            &world_normal(render, &model.calc_normal(iface, ivert)),
+        );
+        let t: Vector4<f32> = model.calc_tangent(iface, ivert);
+        self.varying_tangent.set_column(
+            ivert,
+            &(render.model() * vec3f_to_vec4f(&t.xyz(), 0.0)).xyz(),
+        );
+        self.varying_sign[ivert] = t.w;
+        render.transform() * v
+    }
+    fn fragment(
+        &self,
+        weights: &Vector3<f32>,
+        _model: &Objcracker,
+        ctx: &FragmentContext,
+    ) -> Vector3<f32> {
+        let pos: Vector3<f32> = self.varying_pos * weights;
+        let shadow: f32 = ctx.shadow_factor(&pos);
+        let material = ctx.material;
+        let tc: TexCoord = ctx.tex_coord(&self.varying_uv, weights);
+        let n: Vector3<f32> = tangent_normal(
+            material,
+            &tc,
+            &ctx.sampler,
+            &(self.varying_normal * weights),
+            &(self.varying_tangent * weights),
+            self.varying_sign.dot(weights),
+        );
+        let ns: f32 = material.specular(&tc, &ctx.sampler);
+        let mut light: Vector3<f32> = Vector3::repeat(PHONG_AMBIENT);
+        for (l, radiance) in self.uniform_lights.incident(&pos, shadow) {
+            let r: Vector3<f32> = (n * ((n.dot(&l)) * 2.0) - l).normalize();
+            let spec: f32 = 0f32.max(r.dot(&self.uniform_view_dir)).powf(ns);
+            let diff: f32 = n.dot(&l).max(0.0);
+            light += radiance.component_mul(&(Vector3::repeat(diff) + material.ks() * spec));
+        }
+        material.diffuse(&tc, &ctx.sampler).component_mul(&light)
+    }
+}
+
+impl Display for PhongShaderTbn {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "Phong_Shader::with tangent space normal mapping")
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 恒定的环境光, 乘以基础色与环境光遮蔽
+static PBR_AMBIENT: f32 = 0.03;
+// 非金属的垂直入射菲涅尔反射率
+static PBR_F0: f32 = 0.04;
+
+// 金属度/粗糙度工作流的Cook-Torrance BRDF: GGX法线分布, Smith-Schlick几何遮蔽, Schlick菲涅尔
+// 光源强度视为垂直入射时的辐照度, 故Lambert项不再除以π
+#[derive(Clone)]
+pub struct PbrShader {
+    varying_pos: Matrix3<f32>,
+    varying_uv: Matrix2x3<f32>,
+    varying_normal: Matrix3<f32>,
+    varying_tangent: Matrix3<f32>,
+    varying_sign: Vector3<f32>,
+    uniform_lights: Arc<LightSet>,
+    uniform_eye: Vector3<f32>,
+}
+
+impl PbrShader {
+    pub fn new() -> PbrShader {
+        PbrShader {
+            varying_pos: Default::default(),
+            varying_uv: Default::default(),
+            varying_normal: Default::default(),
+            varying_tangent: Default::default(),
+            varying_sign: Vector3::repeat(1.0),
+            uniform_lights: Arc::new(LightSet::default()),
+            uniform_eye: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+impl ShaderPayload for PbrShader {
+    fn bind(&mut self, uniforms: &FrameUniforms) {
+        self.uniform_lights = Arc::clone(&uniforms.lights);
+        self.uniform_eye = uniforms.eye;
+    }
+    fn vertex(
+        &mut self,
+        iface: usize,
+        ivert: usize,
+        model: &Objcracker,
+        render: &Delusion,
+    ) -> Vector4<f32> {
+        let v: Vector4<f32> = vec3f_to_vec4f(&model.calc_vert(iface, ivert), 1.0);
+        self.varying_pos
+            .set_column(ivert, &(render.model() * v).xyz());
+        self.varying_uv
+            .set_column(ivert, &model.calc_uv(iface, ivert));
+        self.varying_normal.set_column(
+            ivert,
+            &world_normal(render, &model.calc_normal(iface, ivert)),
+        );
+        let t: Vector4<f32> = model.calc_tangent(iface, ivert);
+        self.varying_tangent.set_column(
+            ivert,
+            &(render.model() * vec3f_to_vec4f(&t.xyz(), 0.0)).xyz(),
+        );
+        self.varying_sign[ivert] = t.w;
+        render.transform() * v
+    }
+    fn fragment(
+        &self,
+        weights: &Vector3<f32>,
+        _model: &Objcracker,
+        ctx: &FragmentContext,
+    ) -> Vector3<f32> {
+        let pos: Vector3<f32> = self.varying_pos * weights;
+        let shadow: f32 = ctx.shadow_factor(&pos);
+        let material = ctx.material;
+        let tc: TexCoord = ctx.tex_coord(&self.varying_uv, weights);
+        let n: Vector3<f32> = tangent_normal(
+            material,
+            &tc,
+            &ctx.sampler,
+            &(self.varying_normal * weights),
+            &(self.varying_tangent * weights),
+            self.varying_sign.dot(weights),
+        );
+        let v: Vector3<f32> = (self.uniform_eye - pos)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or(n);
+
+        let base: Vector3<f32> = material
+            .diffuse(&tc, &ctx.sampler)
+            .map(|c| (c / 255.0).clamp(0.0, 1.0));
+        let metallic: f32 = material.metallic(&tc, &ctx.sampler).clamp(0.0, 1.0);
+        // 粗糙度过小时高光退化为无穷窄的尖峰, 光栅化下只会闪烁
+        let roughness: f32 = material.roughness(&tc, &ctx.sampler).clamp(0.04, 1.0);
+        let a2: f32 = (roughness * roughness).powi(2);
+        let k: f32 = (roughness + 1.0).powi(2) / 8.0;
+        let f0: Vector3<f32> = Vector3::repeat(PBR_F0).lerp(&base, metallic);
+        let nv: f32 = n.dot(&v).max(1e-4);
+
+        let mut color: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
+        for (l, radiance) in self.uniform_lights.incident(&pos, shadow) {
+            let nl: f32 = n.dot(&l);
+            if nl <= 0.0 {
+                continue;
+            }
+            let h: Vector3<f32> = (l + v).try_normalize(f32::EPSILON).unwrap_or(n);
+            let nh: f32 = n.dot(&h).max(0.0);
+            let vh: f32 = v.dot(&h).max(0.0);
+            let d: f32 = a2 / (std::f32::consts::PI * (nh * nh * (a2 - 1.0) + 1.0).powi(2));
+            let g: f32 = nv / (nv * (1.0 - k) + k) * nl / (nl * (1.0 - k) + k);
+            let f: Vector3<f32> = f0 + (Vector3::repeat(1.0) - f0) * (1.0 - vh).powi(5);
+            let specular: Vector3<f32> = f * (d * g / (4.0 * nv * nl));
+            let kd: Vector3<f32> = (Vector3::repeat(1.0) - f) * (1.0 - metallic);
+            let brdf: Vector3<f32> = kd.component_mul(&base) + specular * std::f32::consts::PI;
+            color += brdf.component_mul(&radiance) * nl;
+        }
+        color += base * (PBR_AMBIENT * material.occlusion(&tc, &ctx.sampler));
+        color += material.emissive(&tc, &ctx.sampler);
+        // 输出线性HDR颜色, 超出白色的部分交由色调映射处理
+        color * 255.0
+    }
+}
+
+impl Display for PbrShader {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "PBR_Shader::metallic roughness")
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone)]
+pub struct PhongShaderNm {
+    varying_pos: Matrix3<f32>,
+    varying_uv: Matrix2x3<f32>,
+    uniform_lights: Arc<LightSet>,
+    uniform_m: Matrix4<f32>,
+    uniform_mit: Matrix4<f32>,
+    uniform_model_it: Matrix4<f32>,
+}
+
+impl PhongShaderNm {
+    pub fn new() -> PhongShaderNm {
+        PhongShaderNm {
+            varying_pos: Default::default(),
+            varying_uv: Default::default(),
+            uniform_lights: Arc::new(LightSet::default()),
+            uniform_m: Matrix4::<f32>::identity(),
+            uniform_mit: Matrix4::<f32>::identity(),
+            uniform_model_it: Matrix4::<f32>::identity(),
+        }
+    }
+}
+
+impl ShaderPayload for PhongShaderNm {
+    fn bind(&mut self, uniforms: &FrameUniforms) {
+        self.uniform_lights = Arc::clone(&uniforms.lights);
+        self.uniform_m = uniforms.view_projection;
+        self.uniform_mit = uniforms.view_projection_it;
+        self.uniform_model_it = uniforms.model_it;
+    }
+    fn vertex(
+        &mut self,
+        iface: usize,
+        ivert: usize,
+        model: &Objcracker,
+        render: &Delusion,
+    ) -> Vector4<f32> {
+        let pos: Vector4<f32> =
+            render.model() * vec3f_to_vec4f(&model.calc_vert(iface, ivert), 1.0);
+        self.varying_pos.set_column(ivert, &pos.xyz());
+        self.varying_uv
+            .set_column(ivert, &model.calc_uv(iface, ivert));
+        let vt: Vector4<f32> =
+            render.transform() * vec3f_to_vec4f(&model.calc_vert(iface, ivert), 1.0);
+        vt
+    }
+    fn fragment(
+        &self,
+        weights: &Vector3<f32>,
+        _model: &Objcracker,
+        ctx: &FragmentContext,
+    ) -> Vector3<f32> {
+        let pos: Vector3<f32> = self.varying_pos * weights;
+        let shadow: f32 = ctx.shadow_factor(&pos);
+        let material = ctx.material;
+        let tc: TexCoord = ctx.tex_coord(&self.varying_uv, weights);
+        let nm: Vector3<f32> = material.normal(&tc, &ctx.sampler);
+        let nm: Vector3<f32> = (self.uniform_model_it * vec3f_to_vec4f(&nm, 0.0))
+            .xyz()
+            .normalize();
+        let n: Vector3<f32> = (self.uniform_mit * vec3f_to_vec4f(&nm, 1.0))
+            .xyz()
+            .normalize();
+        let mut light: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
+        for (l, radiance) in self.uniform_lights.incident(&pos, shadow) {
+            let l: Vector3<f32> = (self.uniform_m * vec3f_to_vec4f(&l, 1.0)).xyz().normalize();
+            light += radiance * n.dot(&l).max(0.0);
+        }
+        material.diffuse(&tc, &ctx.sampler).component_mul(&light)
+    }
+}
+
+impl Display for PhongShaderNm {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "Phong_Shader::with normal mapping")
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone)]
+pub struct PhongShaderModel {
+    varying_pos: Matrix3<f32>,
+    varying_normal: Matrix3<f32>,
+    uniform_lights: Arc<LightSet>,
+}
+
+impl PhongShaderModel {
+    pub fn new() -> PhongShaderModel {
+        PhongShaderModel {
+            varying_pos: Default::default(),
+            varying_normal: Default::default(),
+            uniform_lights: Arc::new(LightSet::default()),
+        }
+    }
+}
+
+impl ShaderPayload for PhongShaderModel {
+    fn bind(&mut self, uniforms: &FrameUniforms) {
+        self.uniform_lights = Arc::clone(&uniforms.lights);
+    }
+    fn vertex(
+        &mut self,
+        iface: usize,
+        ivert: usize,
+        model: &Objcracker,
+        render: &Delusion,
+    ) -> Vector4<f32> {
+        let pos: Vector4<f32> =
+            render.model() * vec3f_to_vec4f(&model.calc_vert(iface, ivert), 1.0);
+        self.varying_pos.set_column(ivert, &pos.xyz());
+        self.varying_normal.set_column(
+            ivert,
+            &world_normal(render, &model.calc_normal(iface, ivert)),
+        );
+        let vt: Vector4<f32> =
+            render.transform() * vec3f_to_vec4f(&model.calc_vert(iface, ivert), 1.0);
+        vt
+    }
+    fn fragment(
+        &self,
+        weights: &Vector3<f32>,
+        _model: &Objcracker,
+        ctx: &FragmentContext,
+    ) -> Vector3<f32> {
+        let pos: Vector3<f32> = self.varying_pos * weights;
+        let shadow: f32 = ctx.shadow_factor(&pos);
+        let normal: Vector3<f32> = self.varying_normal * weights;
+        let mut light: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
+        for (l, radiance) in self.uniform_lights.incident(&pos, shadow) {
+            light += radiance * normal.dot(&l).max(0.0);
+        }
+        Vector3::new(255.0, 255.0, 255.0).component_mul(&light)
+    }
+}
+
+impl Display for PhongShaderModel {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "Phong_Shader:::model mode")
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone)]
+pub struct PhongShaderSpec {
+    varying_pos: Matrix3<f32>,
+    varying_normal: Matrix3<f32>,
+    uniform_lights: Arc<LightSet>,
+    varying_uv: Matrix2x3<f32>,
+    uniform_m: Matrix4<f32>,
+}
+
+impl PhongShaderSpec {
+    pub fn new() -> PhongShaderSpec {
+        PhongShaderSpec {
+            varying_pos: Default::default(),
+            varying_normal: Default::default(),
+            uniform_lights: Arc::new(LightSet::default()),
+            varying_uv: Default::default(),
+            uniform_m: Matrix4::<f32>::identity(),
+        }
+    }
+}
+
+impl ShaderPayload for PhongShaderSpec {
+    fn bind(&mut self, uniforms: &FrameUniforms) {
+        self.uniform_lights = Arc::clone(&uniforms.lights);
+        self.uniform_m = uniforms.view_projection;
+    }
+    fn vertex(
+        &mut self,
+        iface: usize,
+        ivert: usize,
+        model: &Objcracker,
+        render: &Delusion,
+    ) -> Vector4<f32> {
+        let pos: Vector4<f32> =
+            render.model() * vec3f_to_vec4f(&model.calc_vert(iface, ivert), 1.0);
+        self.varying_pos.set_column(ivert, &pos.xyz());
+        self.varying_normal.set_column(
+            ivert,
+            &world_normal(render, &model.calc_normal(iface, ivert)),
+        );
+        self.varying_uv
+            .set_column(ivert, &model.calc_uv(iface, ivert));
+        let vt: Vector4<f32> =
+            render.transform() * vec3f_to_vec4f(&model.calc_vert(iface, ivert), 1.0);
+        vt
+    }
+    fn fragment(
+        &self,
+        weights: &Vector3<f32>,
+        _model: &Objcracker,
+        ctx: &FragmentContext,
+    ) -> Vector3<f32> {
+        let pos: Vector3<f32> = self.varying_pos * weights;
+        let shadow: f32 = ctx.shadow_factor(&pos);
+        let material = ctx.material;
+        let tc: TexCoord = ctx.tex_coord(&self.varying_uv, weights);
+        let n: Vector3<f32> = self.varying_normal * weights;
+        let ns: f32 = material.specular(&tc, &ctx.sampler);
+        let mut light: Vector3<f32> = Vector3::repeat(PHONG_AMBIENT);
+        for (l, radiance) in self.uniform_lights.incident(&pos, shadow) {
+            let l: Vector3<f32> = (self.uniform_m * vec3f_to_vec4f(&l, 1.0)).xyz().normalize();
+            let r: Vector3<f32> = (n * ((n.dot(&l)) * 2.0) - l).normalize();
+            let spec: f32 = 0f32.max(r.z).powf(ns);
+            let diff: f32 = n.dot(&l).max(0.0);
+            light += radiance.component_mul(&(Vector3::repeat(diff) + material.ks() * spec));
+        }
+        material.diffuse(&tc, &ctx.sampler).component_mul(&light)
+    }
+}
+
+impl Display for PhongShaderSpec {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "Phong_Shader::with specular mapping.")
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone)]
+pub struct PhongShaderDiff {
+    varying_pos: Matrix3<f32>,
+    varying_normal: Matrix3<f32>,
+    uniform_lights: Arc<LightSet>,
+    varying_uv: Matrix2x3<f32>,
+}
+
+impl PhongShaderDiff {
+    pub fn new() -> PhongShaderDiff {
+        PhongShaderDiff {
+            varying_pos: Default::default(),
+            varying_normal: Default::default(),
+            uniform_lights: Arc::new(LightSet::default()),
+            varying_uv: Default::default(),
+        }
+    }
+}
+
+impl ShaderPayload for PhongShaderDiff {
+    fn bind(&mut self, uniforms: &FrameUniforms) {
+        self.uniform_lights = Arc::clone(&uniforms.lights);
+    }
+    fn vertex(
+        &mut self,
+        iface: usize,
+        ivert: usize,
+        model: &Objcracker,
+        render: &Delusion,
+    ) -> Vector4<f32> {
+        let pos: Vector4<f32> =
+            render.model() * vec3f_to_vec4f(&model.calc_vert(iface, ivert), 1.0);
+        self.varying_pos.set_column(ivert, &pos.xyz());
+        self.varying_normal.set_column(
+            ivert,
+            &world_normal(render, &model.calc_normal(iface, ivert)),
+        );
+        self.varying_uv
+            .set_column(ivert, &model.calc_uv(iface, ivert));
+        let vt: Vector4<f32> =
+            render.transform() * vec3f_to_vec4f(&model.calc_vert(iface, ivert), 1.0);
+        vt
+    }
+    fn fragment(
+        &self,
+        weights: &Vector3<f32>,
+        _model: &Objcracker,
+        ctx: &FragmentContext,
+    ) -> Vector3<f32> {
+        let pos: Vector3<f32> = self.varying_pos * weights;
+        let shadow: f32 = ctx.shadow_factor(&pos);
+        let material = ctx.material;
+        let tc: TexCoord = ctx.tex_coord(&self.varying_uv, weights);
+        let normal: Vector3<f32> = self.varying_normal * weights;
+        let mut light: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
+        for (l, radiance) in self.uniform_lights.incident(&pos, shadow) {
+            light += radiance * normal.dot(&l).max(0.0);
+        }
+        material.diffuse(&tc, &ctx.sampler).component_mul(&light)
+    }
+}
+
+impl Display for PhongShaderDiff {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "Phong_Shader:::diffuse mapping")
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone)]
+pub struct DepthShader {
+    varying_tri: Matrix3<f32>,
+}
+
+impl DepthShader {
+    pub fn new() -> DepthShader {
+        DepthShader {
+            varying_tri: Default::default(),
+        }
+    }
+}
+
+impl ShaderPayload for DepthShader {
+    fn vertex(
+        &mut self,
+        iface: usize,
+        ivert: usize,
+        model: &Objcracker,
+        render: &Delusion,
+    ) -> Vector4<f32> {
+        let vt: Vector4<f32> =
+            render.transform() * vec3f_to_vec4f(&model.calc_vert(iface, ivert), 1.0);
+        self.varying_tri.set_column(ivert, &(vt / vt[3]).xyz());
+        vt
+    }
+    fn fragment(
+        &self,
+        weights: &Vector3<f32>,
+        _model: &Objcracker,
+        _ctx: &FragmentContext,
+    ) -> Vector3<f32> {
+        let p: Vector3<f32> = self.varying_tri * weights;
+        Vector3::new(255.0, 255.0, 255.0) * (p.z / 255.0)
+    }
+    // varying_tri保存的是透视除法后的屏幕坐标, 需按屏幕空间插值
+    fn interpolation(&self) -> Interpolation {
+        Interpolation::Screen
+    }
+}
+
+impl Display for DepthShader {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "Depth_Shader")
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 着色器的默认值即new()的结果, uniform在绘制时经bind传入
+macro_rules! impl_default_from_new {
+    ($($shader:ident),*) => {
+        $(impl Default for $shader {
+            fn default() -> $shader {
+                $shader::new()
+            }
+        })*
+    };
+}
+
+impl_default_from_new!(
+    WeirdShader,
+    PhongShaderModel,
+    GouraudShader,
+    PhongShaderDiff,
+    PhongShaderNm,
+    PhongShaderSpec,
+    PhongShaderNmSpec,
+    PhongShaderTbn,
+    PbrShader,
+    DepthShader
+);
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ShaderKind {
+    Weird,
+    Model,
+    Gouraud,
+    Diff,
+    Nm,
+    Spec,
+    NmSpec,
+    Tbn,
+    Pbr,
+    Depth,
+}
+
+impl ShaderKind {
+    pub fn from_name(name: &str) -> Option<ShaderKind> {
+        match name {
+            "weird" => Some(ShaderKind::Weird),
+            "model" => Some(ShaderKind::Model),
+            "gouraud" => Some(ShaderKind::Gouraud),
+            "diff" => Some(ShaderKind::Diff),
+            "nm" => Some(ShaderKind::Nm),
+            "spec" => Some(ShaderKind::Spec),
+            "nmspec" => Some(ShaderKind::NmSpec),
+            "tbn" => Some(ShaderKind::Tbn),
+            "pbr" => Some(ShaderKind::Pbr),
+            "depth" => Some(ShaderKind::Depth),
+            _ => None,
+        }
+    }
+
+    // uniform在绘制时经bind传入
+    pub fn build(&self) -> Box<dyn ShaderPayload> {
+        match self {
+            ShaderKind::Weird => Box::new(WeirdShader::new()),
+            ShaderKind::Model => Box::new(PhongShaderModel::new()),
+            ShaderKind::Gouraud => Box::new(GouraudShader::new()),
+            ShaderKind::Diff => Box::new(PhongShaderDiff::new()),
+            ShaderKind::Nm => Box::new(PhongShaderNm::new()),
+            ShaderKind::Spec => Box::new(PhongShaderSpec::new()),
+            ShaderKind::NmSpec => Box::new(PhongShaderNmSpec::new()),
+            ShaderKind::Tbn => Box::new(PhongShaderTbn::new()),
+            ShaderKind::Pbr => Box::new(PbrShader::new()),
+            ShaderKind::Depth => Box::new(DepthShader::new()),
+        }
+    }
+}
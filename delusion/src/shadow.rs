@@ -0,0 +1,93 @@
+use nalgebra::{Matrix4, Vector3, Vector4};
+
+use crate::delusion::DrawItem;
+use crate::graphics;
+use crate::graphics::ShadowOptions;
+use crate::tile::RenderTile;
+use crate::transform::*;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+static UP: Vector3<f32> = Vector3::new(0.0, 1.0, 0.0);
+static UP_FALLBACK: Vector3<f32> = Vector3::new(0.0, 0.0, 1.0);
+static ORIGIN: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 从光源方向正交投影得到的深度图, 深度越大离光源越近
+pub struct ShadowMap {
+    options: ShadowOptions,
+    m_shadow: Matrix4<f32>,
+    target: RenderTile,
+}
+
+impl ShadowMap {
+    pub fn new(options: ShadowOptions) -> ShadowMap {
+        ShadowMap {
+            options,
+            m_shadow: Matrix4::<f32>::identity(),
+            target: RenderTile::new(0, 0, options.size, options.size),
+        }
+    }
+
+    // 深度预渲染: 以光源方向为视线, 将所有绘制项按各自的模型矩阵写入深度图
+    pub fn render(&mut self, items: &[DrawItem], light: &Vector3<f32>) {
+        let size = self.options.size;
+        let up = match light.normalize().dot(&UP).abs() > 0.999 {
+            true => &UP_FALLBACK,
+            false => &UP,
+        };
+        let mut m_ortho = Matrix4::<f32>::identity();
+        for i in 0..3 {
+            m_ortho[(i, i)] = 1.0 / self.options.extent;
+        }
+        self.m_shadow = graphics::calc_m_viewport(size, size, 1.0)
+            * m_ortho
+            * graphics::calc_m_camera(light, &ORIGIN, up);
+
+        self.target.clear_depth();
+        for item in items {
+            let m: Matrix4<f32> = self.m_shadow * item.transform;
+            let model = item.model;
+            for i in 0..model.nfaces() {
+                let mut pts: Vector3<Vector4<f32>> = Default::default();
+                for j in 0..3 {
+                    pts[j] = m * vec3f_to_vec4f(&model.calc_vert(i, j), 1.0);
+                }
+                self.target.rasterize_depth(&pts);
+            }
+        }
+    }
+
+    // 世界空间中一点的可见度, 1为完全受光, 0为完全处于阴影中
+    // 超出深度图范围的点视为受光
+    pub fn visibility(&self, p: &Vector3<f32>) -> f32 {
+        let q: Vector4<f32> = self.m_shadow * vec3f_to_vec4f(p, 1.0);
+        let depth = (q.z / q.w + 0.5).clamp(0.0, 255.0) + self.options.bias;
+        let (cx, cy) = ((q.x / q.w).round() as i64, (q.y / q.w).round() as i64);
+        let (size, r) = (self.options.size as i64, self.options.pcf as i64);
+
+        let mut lit: usize = 0;
+        let mut total: usize = 0;
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let (x, y) = (cx + dx, cy + dy);
+                total += 1;
+                if x < 0
+                    || y < 0
+                    || x >= size
+                    || y >= size
+                    || depth >= self.target.get_depth(x as usize, y as usize)
+                {
+                    lit += 1;
+                }
+            }
+        }
+        lit as f32 / total as f32
+    }
+
+    #[inline]
+    pub fn options(&self) -> &ShadowOptions {
+        &self.options
+    }
+}
@@ -0,0 +1,363 @@
+use nalgebra::{Vector2, Vector3, Vector4};
+
+use objcracker::Objcracker;
+
+use crate::graphics;
+use crate::graphics::*;
+use crate::shader::{FragmentContext, ShaderPayload};
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 由屏幕空间重心坐标得到传给片元着色器的权重, 裁剪后的子三角形需还原到原三角形
+#[inline(always)]
+fn varying_weights(
+    weights: &Vector3<f32>,
+    pts: &Vector3<Vector4<f32>>,
+    bary: Option<&ClipBary>,
+    interpolation: Interpolation,
+) -> Vector3<f32> {
+    match (interpolation, bary) {
+        (Interpolation::Perspective, None) => perspective_correct(weights, pts),
+        (Interpolation::Perspective, Some(b)) => b.clip * perspective_correct(weights, pts),
+        (Interpolation::Screen, None) => *weights,
+        (Interpolation::Screen, Some(b)) => b.screen * weights,
+    }
+}
+
+// 采样点处的深度, 越大越近
+#[inline(always)]
+fn sample_depth(pts: &Vector3<Vector4<f32>>, weights: &Vector3<f32>) -> f32 {
+    let z: f32 = pts[0][2] * weights.x + pts[1][2] * weights.y + pts[2][2] * weights.z;
+    let w: f32 = pts[0][3] * weights.x + pts[1][3] * weights.y + pts[2][3] * weights.z;
+    (z / w + 0.5).clamp(0.0, 255.0)
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 帧缓冲中的一块矩形区域, 整帧即为原点处覆盖全屏的区块
+// 颜色缓冲存放线性空间的HDR颜色(255为白), 不做截断, 由色调映射转为显示颜色
+// 颜色缓冲按行自上而下存储(y轴翻转), 深度与MSAA缓冲自下而上存储
+// 开启MSAA时光栅化只写采样缓冲, 需调用resolve()解析到颜色与深度缓冲
+pub struct RenderTile {
+    x0: usize,
+    y0: usize,
+    w: usize,
+    h: usize,
+    f_buffer: Vec<Vector3<f32>>,
+    d_buffer: Vec<f32>,
+    msaa_tensor: MsaaTensor,
+}
+
+impl RenderTile {
+    pub fn new(x0: usize, y0: usize, w: usize, h: usize) -> RenderTile {
+        RenderTile {
+            x0,
+            y0,
+            w,
+            h,
+            f_buffer: vec![Vector3::repeat(0.0); w * h],
+            d_buffer: vec![f32::MIN; w * h],
+            msaa_tensor: MsaaTensor::new(0, 0),
+        }
+    }
+
+    // 按采样数重新分配采样缓冲, 0表示关闭MSAA
+    pub fn set_msaa_samples(&mut self, samples: usize) {
+        if self.msaa_tensor.samples() != samples {
+            self.msaa_tensor = MsaaTensor::new(self.w * self.h, samples);
+        }
+    }
+
+    // 从整帧中拷贝出区块
+    pub fn split_from(frame: &RenderTile, x0: usize, y0: usize, w: usize, h: usize) -> RenderTile {
+        let mut tile = RenderTile::new(x0, y0, w, h);
+        tile.set_msaa_samples(frame.msaa_tensor.samples());
+        for y in y0..y0 + h {
+            let (src, dst) = (frame.color_index(x0, y), tile.color_index(x0, y));
+            tile.f_buffer[dst..dst + w].copy_from_slice(&frame.f_buffer[src..src + w]);
+            let (src, dst) = (frame.index(x0, y), tile.index(x0, y));
+            tile.d_buffer[dst..dst + w].copy_from_slice(&frame.d_buffer[src..src + w]);
+            tile.msaa_tensor
+                .copy_pixels(dst, &frame.msaa_tensor, src, w);
+        }
+        tile
+    }
+
+    // 将区块写回整帧
+    pub fn merge_into(&self, frame: &mut RenderTile) {
+        let (x0, w) = (self.x0, self.w);
+        for y in self.y0..self.y0 + self.h {
+            let (src, dst) = (self.color_index(x0, y), frame.color_index(x0, y));
+            frame.f_buffer[dst..dst + w].copy_from_slice(&self.f_buffer[src..src + w]);
+            let (src, dst) = (self.index(x0, y), frame.index(x0, y));
+            frame.d_buffer[dst..dst + w].copy_from_slice(&self.d_buffer[src..src + w]);
+            frame
+                .msaa_tensor
+                .copy_pixels(dst, &self.msaa_tensor, src, w);
+        }
+    }
+
+    /////////////////////////////////////////////////////////////////////////////////
+
+    // 包围盒裁剪到区块范围内, 串行与分块路径逐像素计算完全一致
+    // bary不为空时pts为裁剪后的子三角形, pattern为空时不做多重采样
+    // 以全局坐标对齐的2x2像素块遍历, 块内像素共享插值权重的屏幕空间偏导
+    pub fn rasterize_tri(
+        &mut self,
+        pts: &Vector3<Vector4<f32>>,
+        bary: Option<&ClipBary>,
+        shader: &dyn ShaderPayload,
+        model: &Objcracker,
+        ctx: &FragmentContext,
+        pattern: &[Vector2<f32>],
+    ) {
+        let mut bboxmin: [f32; 2] = [f32::MAX, f32::MAX];
+        let mut bboxmax: [f32; 2] = [f32::MIN, f32::MIN];
+        graphics::sample_bounding_box(pts, pattern, &mut bboxmin, &mut bboxmax);
+        let xmin = bboxmin[0].ceil().max(self.x0 as f32) as usize;
+        let xmax = bboxmax[0].ceil().min((self.x0 + self.w) as f32) as usize;
+        let ymin = bboxmin[1].ceil().max(self.y0 as f32) as usize;
+        let ymax = bboxmax[1].ceil().min((self.y0 + self.h) as f32) as usize;
+        let interpolation = shader.interpolation();
+        let screen = [pts[0] / pts[0][3], pts[1] / pts[1][3], pts[2] / pts[2][3]];
+        let weights_at = |x: f32, y: f32| barycentric(&screen[0], &screen[1], &screen[2], x, y);
+
+        let mut ctx = *ctx;
+        let mut passed: Vec<usize> = Vec::with_capacity(pattern.len());
+        for qx in ((xmin & !1)..xmax).step_by(2) {
+            for qy in ((ymin & !1)..ymax).step_by(2) {
+                // 粗粒度偏导: 块内左下像素与其右侧, 上方像素之差, 覆盖与否均参与计算
+                let (fx, fy) = (qx as f32, qy as f32);
+                let w00 = varying_weights(&weights_at(fx, fy), pts, bary, interpolation);
+                let w10 = varying_weights(&weights_at(fx + 1.0, fy), pts, bary, interpolation);
+                let w01 = varying_weights(&weights_at(fx, fy + 1.0), pts, bary, interpolation);
+                ctx.ddx = w10 - w00;
+                ctx.ddy = w01 - w00;
+
+                for x in qx.max(xmin)..(qx + 2).min(xmax) {
+                    for y in qy.max(ymin)..(qy + 2).min(ymax) {
+                        if pattern.is_empty() {
+                            let weights = weights_at(x as f32, y as f32);
+                            if !interior(&weights) {
+                                continue;
+                            }
+                            let dep = sample_depth(pts, &weights);
+                            if self.get_depth(x, y) <= dep {
+                                self.set_depth(x, y, dep);
+                                self.set_color(
+                                    x,
+                                    y,
+                                    &shader.fragment(
+                                        &varying_weights(&weights, pts, bary, interpolation),
+                                        model,
+                                        &ctx,
+                                    ),
+                                );
+                            }
+                            continue;
+                        }
+
+                        let ipixel = self.index(x, y);
+                        let mut covered: usize = 0;
+                        let mut centroid: Vector3<f32> = Vector3::repeat(0.0);
+                        passed.clear();
+
+                        // 逐采样点做覆盖与深度测试
+                        for (isample, offset) in pattern.iter().enumerate() {
+                            let weights = weights_at(x as f32 + offset.x, y as f32 + offset.y);
+                            if !interior(&weights) {
+                                continue;
+                            }
+                            covered += 1;
+                            centroid += weights;
+                            let dep = sample_depth(pts, &weights);
+                            if self.msaa_tensor.dept(ipixel, isample) <= dep {
+                                self.msaa_tensor.set_dept(ipixel, isample, dep);
+                                passed.push(isample);
+                            }
+                        }
+                        if passed.is_empty() {
+                            continue;
+                        }
+
+                        // 每像素只着色一次: 全覆盖时取像素中心, 部分覆盖时取覆盖采样点的质心
+                        let weights = match covered == pattern.len() {
+                            true => weights_at(x as f32, y as f32),
+                            false => centroid / covered as f32,
+                        };
+                        let color = shader.fragment(
+                            &varying_weights(&weights, pts, bary, interpolation),
+                            model,
+                            &ctx,
+                        );
+                        for &isample in &passed {
+                            self.msaa_tensor.set_colo(ipixel, isample, &color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // 只写深度的光栅化, 用于阴影贴图等深度预渲染, pts需已完成透视除法(w=1)
+    pub fn rasterize_depth(&mut self, pts: &Vector3<Vector4<f32>>) {
+        let mut bboxmin: [f32; 2] = [f32::MAX, f32::MAX];
+        let mut bboxmax: [f32; 2] = [f32::MIN, f32::MIN];
+        graphics::bounding_box(pts, &mut bboxmin, &mut bboxmax);
+        let xmin = bboxmin[0].ceil().max(self.x0 as f32) as usize;
+        let xmax = bboxmax[0].ceil().min((self.x0 + self.w) as f32) as usize;
+        let ymin = bboxmin[1].ceil().max(self.y0 as f32) as usize;
+        let ymax = bboxmax[1].ceil().min((self.y0 + self.h) as f32) as usize;
+        for x in xmin..xmax {
+            for y in ymin..ymax {
+                let weights = barycentric(&pts[0], &pts[1], &pts[2], x as f32, y as f32);
+                if interior(&weights) {
+                    let dep = sample_depth(pts, &weights);
+                    if self.get_depth(x, y) <= dep {
+                        self.set_depth(x, y, dep);
+                    }
+                }
+            }
+        }
+    }
+
+    // 将采样缓冲解析到颜色与深度缓冲
+    pub fn resolve(&mut self) {
+        if self.msaa_tensor.samples() == 0 {
+            return;
+        }
+        for y in self.y0..self.y0 + self.h {
+            for x in self.x0..self.x0 + self.w {
+                let (color, depth) = self.msaa_tensor.resolve(self.index(x, y));
+                self.set_color(x, y, &color);
+                self.set_depth(x, y, depth);
+            }
+        }
+    }
+
+    /////////////////////////////////////////////////////////////////////////////////
+
+    pub fn clear_color(&mut self, color: &Vector3<f32>) {
+        self.f_buffer.fill(*color);
+        self.msaa_tensor.clear_colo(color);
+    }
+
+    pub fn clear_depth(&mut self) {
+        self.d_buffer.fill(f32::MIN);
+        self.msaa_tensor.clear_dept();
+    }
+
+    pub fn set_color(&mut self, x: usize, y: usize, color: &Vector3<f32>) {
+        if !self.contains(x, y) {
+            return;
+        }
+        let index = self.color_index(x, y);
+        self.f_buffer[index] = *color;
+    }
+
+    pub fn get_depth(&self, x: usize, y: usize) -> f32 {
+        if !self.contains(x, y) {
+            return 0.0;
+        }
+        self.d_buffer[self.index(x, y)]
+    }
+
+    pub fn set_depth(&mut self, x: usize, y: usize, value: f32) {
+        if !self.contains(x, y) {
+            return;
+        }
+        let index = self.index(x, y);
+        self.d_buffer[index] = value;
+    }
+
+    /////////////////////////////////////////////////////////////////////////////////
+
+    #[inline]
+    fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x0 && y >= self.y0 && x < self.x0 + self.w && y < self.y0 + self.h
+    }
+    #[inline]
+    fn index(&self, x: usize, y: usize) -> usize {
+        (x - self.x0) + (y - self.y0) * self.w
+    }
+    #[inline]
+    fn color_index(&self, x: usize, y: usize) -> usize {
+        (self.h - 1 - (y - self.y0)) * self.w + (x - self.x0)
+    }
+    #[inline]
+    pub fn f_buffer(&self) -> &Vec<Vector3<f32>> {
+        &self.f_buffer
+    }
+    #[inline]
+    pub fn d_buffer(&self) -> &Vec<f32> {
+        &self.d_buffer
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use objcracker::{Material, Sampler};
+
+    use super::*;
+    use crate::delusion::Delusion;
+
+    // 红色为屏幕空间插值的x坐标, 绿色恒为1
+    #[derive(Clone)]
+    struct CoordShader {
+        xs: Vector3<f32>,
+    }
+
+    impl ShaderPayload for CoordShader {
+        fn vertex(&mut self, _: usize, _: usize, _: &Objcracker, _: &Delusion) -> Vector4<f32> {
+            unimplemented!()
+        }
+        fn fragment(
+            &self,
+            weights: &Vector3<f32>,
+            _: &Objcracker,
+            _: &FragmentContext,
+        ) -> Vector3<f32> {
+            Vector3::new(self.xs.dot(weights), 1.0, 0.0)
+        }
+        fn interpolation(&self) -> Interpolation {
+            Interpolation::Screen
+        }
+    }
+
+    #[test]
+    fn partial_coverage_resolves_to_weighted_average() {
+        // 竖直边x=1.2穿过第1列像素, 4x采样点的x偏移为-0.125, 0.375, -0.375, 0.125, 其中3个在边的左侧
+        let pts = Vector3::new(
+            Vector4::new(-10.0, -10.0, 0.0, 1.0),
+            Vector4::new(1.2, -10.0, 0.0, 1.0),
+            Vector4::new(1.2, 20.0, 0.0, 1.0),
+        );
+        let shader = CoordShader {
+            xs: Vector3::new(-10.0, 1.2, 1.2),
+        };
+        let model = Objcracker::new("test");
+        let material = Material::new("test");
+        let ctx = FragmentContext::new(&material, None, Sampler::default());
+        let mut tile = RenderTile::new(0, 0, 4, 4);
+        tile.set_msaa_samples(4);
+        tile.clear_color(&Vector3::repeat(0.0));
+        tile.clear_depth();
+        let pattern = MsaaOptions::X4.sample_pattern();
+        tile.rasterize_tri(&pts, None, &shader, &model, &ctx, &pattern);
+        tile.resolve();
+
+        let color = |x: usize, y: usize| tile.f_buffer[tile.color_index(x, y)];
+        for y in 0..4 {
+            // 全覆盖的像素在像素中心着色
+            assert!((color(0, y) - Vector3::new(0.0, 1.0, 0.0)).norm() < 1e-5);
+            // 部分覆盖的像素在覆盖采样点的质心(x=0.875)处着色一次, 再与清屏颜色按覆盖率平均
+            let expected = Vector3::new(0.875, 1.0, 0.0) * 0.75;
+            assert!((color(1, y) - expected).norm() < 1e-5, "{}", color(1, y));
+            assert_eq!(color(2, y), Vector3::repeat(0.0));
+            assert_eq!(tile.get_depth(1, y), 0.5);
+            assert_eq!(tile.get_depth(2, y), f32::MIN);
+        }
+    }
+}
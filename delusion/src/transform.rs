@@ -99,7 +99,7 @@ pub fn vec3f_to_vec2f(v: &Vector3<f32>) -> Vector2<f32> {
 
 pub fn v34f_to_v33i(v34: &Vector3<Vector4<f32>>) -> Vector3<Vector3<i32>> {
     let mut v33: Vector3<Vector3<i32>> = Default::default();
-    for i in 0..3 as usize {
+    for i in 0..3 {
         v33[i].x = (v34[i].x + 0.5) as i32;
         v33[i].y = (v34[i].y + 0.5) as i32;
         v33[i].z = (v34[i].z + 0.5) as i32;
@@ -111,7 +111,7 @@ pub fn v34f_to_v33i(v34: &Vector3<Vector4<f32>>) -> Vector3<Vector3<i32>> {
 
 pub fn v34f_to_v33f(v34: &Vector3<Vector4<f32>>) -> Vector3<Vector3<f32>> {
     let mut v33: Vector3<Vector3<f32>> = Default::default();
-    for i in 0..3 as usize {
+    for i in 0..3 {
         v33[i].x = v34[i].x;
         v33[i].y = v34[i].y;
         v33[i].z = v34[i].z;
@@ -0,0 +1,85 @@
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+    io,
+};
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io {
+        file: String,
+        source: io::Error,
+    },
+    MalformedNumber {
+        file: String,
+        line: usize,
+        text: String,
+    },
+    MissingComponent {
+        file: String,
+        line: usize,
+        text: String,
+    },
+    IndexOutOfRange {
+        file: String,
+        line: usize,
+        text: String,
+        index: i64,
+    },
+}
+
+impl ObjError {
+    pub fn file(&self) -> &str {
+        match self {
+            ObjError::Io { file, .. }
+            | ObjError::MalformedNumber { file, .. }
+            | ObjError::MissingComponent { file, .. }
+            | ObjError::IndexOutOfRange { file, .. } => file,
+        }
+    }
+
+    // I/O错误没有行号
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            ObjError::Io { .. } => None,
+            ObjError::MalformedNumber { line, .. }
+            | ObjError::MissingComponent { line, .. }
+            | ObjError::IndexOutOfRange { line, .. } => Some(*line),
+        }
+    }
+}
+
+impl Display for ObjError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ObjError::Io { file, source } => write!(f, "{}: {}", file, source),
+            ObjError::MalformedNumber { file, line, text } => {
+                write!(f, "{}:{}: malformed number in `{}`", file, line, text)
+            }
+            ObjError::MissingComponent { file, line, text } => {
+                write!(f, "{}:{}: missing component in `{}`", file, line, text)
+            }
+            ObjError::IndexOutOfRange {
+                file,
+                line,
+                text,
+                index,
+            } => write!(
+                f,
+                "{}:{}: index {} out of range in `{}`",
+                file, line, index, text
+            ),
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
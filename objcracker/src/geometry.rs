@@ -0,0 +1,16 @@
+use nalgebra::Vector3;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 三角形abc在顶点a处的内角(弧度), 相邻边退化时为0
+pub fn corner_angle(a: &Vector3<f32>, b: &Vector3<f32>, c: &Vector3<f32>) -> f32 {
+    let ab = b - a;
+    let ac = c - a;
+    match (
+        ab.try_normalize(f32::EPSILON),
+        ac.try_normalize(f32::EPSILON),
+    ) {
+        (Some(ab), Some(ac)) => ab.dot(&ac).clamp(-1.0, 1.0).acos(),
+        _ => 0.0,
+    }
+}
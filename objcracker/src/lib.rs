@@ -1,3 +1,16 @@
+pub use self::error::ObjError;
+pub use self::material::Material;
+pub use self::normals::{NormalMode, NormalOptions, NormalWeight};
 pub use self::objcracker::Objcracker;
+pub use self::sampler::{AddressMode, Filter, Sampler, TexCoord};
+pub use self::texture::{linear_to_srgb, srgb_to_linear, ColorSpace, Texture};
 
+mod error;
+mod geometry;
+mod material;
+mod normals;
 mod objcracker;
+mod sampler;
+mod tangent;
+mod texture;
+mod triangulate;
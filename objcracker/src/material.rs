@@ -0,0 +1,432 @@
+use std::path::Path;
+
+use nalgebra::Vector3;
+
+use crate::error::ObjError;
+use crate::objcracker::{read_file, LineInfo};
+use crate::sampler::{AddressMode, Sampler, TexCoord};
+use crate::texture::{ColorSpace, Texture};
+
+/////////////////////////////////////////////////////////////////////////////////
+
+pub struct Material {
+    name: String,
+    ka: Vector3<f32>,
+    kd: Vector3<f32>,
+    ks: Vector3<f32>,
+    ns: f32,
+    ke: Vector3<f32>,
+    pr: f32,
+    pm: f32,
+    diffuse_map: Option<Texture>,
+    normal_map: Option<Texture>,
+    specular_map: Option<Texture>,
+    roughness_map: Option<Texture>,
+    metallic_map: Option<Texture>,
+    metallic_roughness_map: Option<Texture>,
+    occlusion_map: Option<Texture>,
+    emissive_map: Option<Texture>,
+}
+
+impl Material {
+    pub fn new(name: &str) -> Material {
+        Material {
+            name: name.to_string(),
+            ka: Vector3::new(0.0, 0.0, 0.0),
+            kd: Vector3::new(1.0, 1.0, 1.0),
+            ks: Vector3::new(1.0, 1.0, 1.0),
+            ns: 0.0,
+            ke: Vector3::new(0.0, 0.0, 0.0),
+            pr: 1.0,
+            pm: 0.0,
+            diffuse_map: None,
+            normal_map: None,
+            specular_map: None,
+            roughness_map: None,
+            metallic_map: None,
+            metallic_roughness_map: None,
+            occlusion_map: None,
+            emissive_map: None,
+        }
+    }
+
+    // 按 {prefix}_diffuse.tga / _nm.tga / _spec.tga 的命名约定查找贴图
+    // 漫反射与自发光贴图按sRGB解码, 其余为线性数据
+    pub fn from_prefix(prefix: &str) -> Material {
+        let mut material = Material::new(prefix);
+        material.diffuse_map = open_texture(
+            &format!("{}_diffuse.tga", prefix),
+            "漫反射",
+            ColorSpace::Srgb,
+        );
+        material.normal_map =
+            open_texture(&format!("{}_nm.tga", prefix), "法线", ColorSpace::Linear);
+        material.specular_map =
+            open_texture(&format!("{}_spec.tga", prefix), "高光", ColorSpace::Linear);
+        // PBR贴图 _roughness / _metallic / _metallic_roughness / _ao / _emissive.tga
+        // 多数模型没有, 找不到时不提示
+        material.roughness_map = Texture::open(format!("{}_roughness.tga", prefix)).ok();
+        material.metallic_map = Texture::open(format!("{}_metallic.tga", prefix)).ok();
+        material.metallic_roughness_map =
+            Texture::open(format!("{}_metallic_roughness.tga", prefix)).ok();
+        material.occlusion_map = Texture::open(format!("{}_ao.tga", prefix)).ok();
+        material.emissive_map =
+            Texture::open_as(format!("{}_emissive.tga", prefix), ColorSpace::Srgb).ok();
+        material
+    }
+
+    /////////////////////////////////////////////////////////////////////////////////
+
+    // map_Kd 与 Kd 相乘, 无贴图时直接使用 Kd
+    pub fn diffuse(&self, tc: &TexCoord, sampler: &Sampler) -> Vector3<f32> {
+        match &self.diffuse_map {
+            Some(map) => sampler.sample(map, tc).component_mul(&self.kd),
+            None => self.kd * 255.0,
+        }
+    }
+
+    pub fn normal(&self, tc: &TexCoord, sampler: &Sampler) -> Vector3<f32> {
+        match &self.normal_map {
+            Some(map) => sampler.sample(map, tc).map(|c| c / 255.0 * 2.0 - 1.0),
+            None => Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    // 高光指数, 无贴图时使用 Ns
+    pub fn specular(&self, tc: &TexCoord, sampler: &Sampler) -> f32 {
+        match &self.specular_map {
+            Some(map) => sampler.sample(map, tc)[0],
+            None => self.ns,
+        }
+    }
+
+    // 以下为PBR参数, 贴图均取0~1, 有贴图时替代对应的标量
+    // 粗糙度优先取金属度粗糙度合并贴图的G通道, 其次取粗糙度贴图的R通道, 无贴图时使用 Pr
+    pub fn roughness(&self, tc: &TexCoord, sampler: &Sampler) -> f32 {
+        match (&self.metallic_roughness_map, &self.roughness_map) {
+            (Some(map), _) => sampler.sample(map, tc)[1] / 255.0,
+            (None, Some(map)) => sampler.sample(map, tc)[0] / 255.0,
+            (None, None) => self.pr,
+        }
+    }
+
+    // 金属度优先取合并贴图的B通道, 其次取金属度贴图的R通道, 无贴图时使用 Pm
+    pub fn metallic(&self, tc: &TexCoord, sampler: &Sampler) -> f32 {
+        match (&self.metallic_roughness_map, &self.metallic_map) {
+            (Some(map), _) => sampler.sample(map, tc)[2] / 255.0,
+            (None, Some(map)) => sampler.sample(map, tc)[0] / 255.0,
+            (None, None) => self.pm,
+        }
+    }
+
+    // 环境光遮蔽取贴图的R通道, 无贴图时不遮蔽
+    pub fn occlusion(&self, tc: &TexCoord, sampler: &Sampler) -> f32 {
+        match &self.occlusion_map {
+            Some(map) => sampler.sample(map, tc)[0] / 255.0,
+            None => 1.0,
+        }
+    }
+
+    // 自发光颜色, 无贴图时使用 Ke
+    pub fn emissive(&self, tc: &TexCoord, sampler: &Sampler) -> Vector3<f32> {
+        match &self.emissive_map {
+            Some(map) => sampler.sample(map, tc) / 255.0,
+            None => self.ke,
+        }
+    }
+
+    // 统一设置所有贴图的寻址模式
+    pub fn set_address_mode(&mut self, address: AddressMode) {
+        for map in [
+            &mut self.diffuse_map,
+            &mut self.normal_map,
+            &mut self.specular_map,
+            &mut self.roughness_map,
+            &mut self.metallic_map,
+            &mut self.metallic_roughness_map,
+            &mut self.occlusion_map,
+            &mut self.emissive_map,
+        ]
+        .iter_mut()
+        .filter_map(|map| map.as_mut())
+        {
+            map.set_address_mode(address);
+        }
+    }
+
+    /////////////////////////////////////////////////////////////////////////////////
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    #[inline]
+    pub fn ka(&self) -> &Vector3<f32> {
+        &self.ka
+    }
+    #[inline]
+    pub fn kd(&self) -> &Vector3<f32> {
+        &self.kd
+    }
+    #[inline]
+    pub fn ks(&self) -> &Vector3<f32> {
+        &self.ks
+    }
+    #[inline]
+    pub fn ns(&self) -> f32 {
+        self.ns
+    }
+    #[inline]
+    pub fn ke(&self) -> &Vector3<f32> {
+        &self.ke
+    }
+    #[inline]
+    pub fn pr(&self) -> f32 {
+        self.pr
+    }
+    #[inline]
+    pub fn pm(&self) -> f32 {
+        self.pm
+    }
+    #[inline]
+    pub fn diffuse_map(&self) -> Option<&Texture> {
+        self.diffuse_map.as_ref()
+    }
+    #[inline]
+    pub fn normal_map(&self) -> Option<&Texture> {
+        self.normal_map.as_ref()
+    }
+    #[inline]
+    pub fn specular_map(&self) -> Option<&Texture> {
+        self.specular_map.as_ref()
+    }
+    #[inline]
+    pub fn roughness_map(&self) -> Option<&Texture> {
+        self.roughness_map.as_ref()
+    }
+    #[inline]
+    pub fn metallic_map(&self) -> Option<&Texture> {
+        self.metallic_map.as_ref()
+    }
+    #[inline]
+    pub fn metallic_roughness_map(&self) -> Option<&Texture> {
+        self.metallic_roughness_map.as_ref()
+    }
+    #[inline]
+    pub fn occlusion_map(&self) -> Option<&Texture> {
+        self.occlusion_map.as_ref()
+    }
+    #[inline]
+    pub fn emissive_map(&self) -> Option<&Texture> {
+        self.emissive_map.as_ref()
+    }
+    #[inline]
+    pub fn texture_status(&self) -> [bool; 3] {
+        [
+            self.diffuse_map.is_some(),
+            self.normal_map.is_some(),
+            self.specular_map.is_some(),
+        ]
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+fn open_texture(path: &str, kind: &str, space: ColorSpace) -> Option<Texture> {
+    match Texture::open_as(path, space) {
+        Ok(texture) => Some(texture),
+        Err(_) => {
+            println!("Delusion::Debug::未找到{}贴图 {}。", kind, path);
+            None
+        }
+    }
+}
+
+// 贴图语句形如 "map_Kd -s 1 1 1 -clamp on file.tga", 跳过选项取出文件名
+fn texture_file(args: &[&str]) -> Option<String> {
+    let mut i = 0;
+    while i < args.len() && args[i].starts_with('-') {
+        let nargs = match args[i] {
+            "-o" | "-s" | "-t" => 3,
+            "-mm" => 2,
+            _ => 1,
+        };
+        i += 1;
+        let mut taken = 0;
+        while taken < nargs && i < args.len() - 1 {
+            // -o/-s/-t 的后两个分量可省略
+            if taken > 0 && args[i].parse::<f32>().is_err() {
+                break;
+            }
+            i += 1;
+            taken += 1;
+        }
+    }
+    match i < args.len() {
+        true => Some(args[i..].join(" ")),
+        false => None,
+    }
+}
+
+// "-clamp on" 时贴图坐标截断到边缘, 否则重复平铺
+fn texture_address(args: &[&str]) -> AddressMode {
+    match args
+        .windows(2)
+        .any(|pair| pair[0] == "-clamp" && pair[1] == "on")
+    {
+        true => AddressMode::ClampToEdge,
+        false => AddressMode::Repeat,
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 解析MTL材质库, 贴图路径相对于MTL文件所在目录
+pub fn parse_mtl(file_name: &str) -> Result<Vec<Material>, ObjError> {
+    let contents = read_file(file_name)?;
+    parse_mtl_contents(file_name, &contents)
+}
+
+pub(crate) fn parse_mtl_contents(
+    file_name: &str,
+    contents: &str,
+) -> Result<Vec<Material>, ObjError> {
+    let dir = Path::new(file_name)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+
+    let mut materials: Vec<Material> = Vec::new();
+    for (iline, line) in contents.lines().enumerate() {
+        let tmp: Vec<&str> = line.split_whitespace().collect();
+        let at = LineInfo {
+            file: file_name,
+            line: iline + 1,
+            text: line,
+        };
+        let key = match tmp.first() {
+            Some(key) => *key,
+            None => continue,
+        };
+        if key == "newmtl" {
+            if tmp.len() < 2 {
+                return Err(at.missing_component());
+            }
+            materials.push(Material::new(&tmp[1..].join(" ")));
+            continue;
+        }
+        let material = match materials.last_mut() {
+            Some(material) => material,
+            None => continue,
+        };
+        match key {
+            "Ka" | "Kd" | "Ks" | "Ke" => {
+                let c = at.parse_floats(&tmp[1..], 3)?;
+                let c = Vector3::new(c[0], c[1], c[2]);
+                match key {
+                    "Ka" => material.ka = c,
+                    "Kd" => material.kd = c,
+                    "Ks" => material.ks = c,
+                    _ => material.ke = c,
+                }
+            }
+            "Ns" => material.ns = at.parse_floats(&tmp[1..], 1)?[0],
+            // PBR扩展: 粗糙度与金属度
+            "Pr" => material.pr = at.parse_floats(&tmp[1..], 1)?[0],
+            "Pm" => material.pm = at.parse_floats(&tmp[1..], 1)?[0],
+            "map_Kd" | "map_Bump" | "map_bump" | "bump" | "norm" | "map_Ks" | "map_Ns"
+            | "map_Pr" | "map_Pm" | "map_RM" | "map_ao" | "map_Ke" => {
+                let file = texture_file(&tmp[1..]).ok_or_else(|| at.missing_component())?;
+                let path = dir.join(&file).display().to_string();
+                let (slot, kind, space) = match key {
+                    "map_Kd" => (&mut material.diffuse_map, "漫反射", ColorSpace::Srgb),
+                    "map_Ks" | "map_Ns" => (&mut material.specular_map, "高光", ColorSpace::Linear),
+                    "map_Pr" => (&mut material.roughness_map, "粗糙度", ColorSpace::Linear),
+                    "map_Pm" => (&mut material.metallic_map, "金属度", ColorSpace::Linear),
+                    // 合并贴图: G通道为粗糙度, B通道为金属度
+                    "map_RM" => (
+                        &mut material.metallic_roughness_map,
+                        "金属度粗糙度",
+                        ColorSpace::Linear,
+                    ),
+                    "map_ao" => (
+                        &mut material.occlusion_map,
+                        "环境光遮蔽",
+                        ColorSpace::Linear,
+                    ),
+                    "map_Ke" => (&mut material.emissive_map, "自发光", ColorSpace::Srgb),
+                    _ => (&mut material.normal_map, "法线", ColorSpace::Linear),
+                };
+                *slot = open_texture(&path, kind, space);
+                if let Some(map) = slot {
+                    map.set_address_mode(texture_address(&tmp[1..]));
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(materials)
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(contents: &str) -> ObjError {
+        match parse_mtl_contents("test.mtl", contents) {
+            Ok(_) => panic!("expected an error for {:?}", contents),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn parse_materials() {
+        let materials = parse_mtl_contents(
+            "test.mtl",
+            "# 两个材质\n\
+             Kd 0 0 0\n\
+             newmtl red\n\
+             Kd 1 0 0\n\
+             Ns 32\n\
+             \n\
+             newmtl brushed metal\n\
+             Ka 0.1 0.1 0.1\n\
+             Ke 0 0 0.5\n\
+             Pr 0.25\n\
+             Pm 1\n\
+             map_Kd -clamp on /nonexistent/metal.tga\n",
+        )
+        .unwrap();
+        assert_eq!(materials.len(), 2);
+
+        let red = &materials[0];
+        assert_eq!(red.name(), "red");
+        assert_eq!(*red.kd(), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(red.ns(), 32.0);
+
+        // 未写出的参数保持默认值, 找不到的贴图留空
+        let metal = &materials[1];
+        assert_eq!(metal.name(), "brushed metal");
+        assert_eq!(*metal.kd(), Vector3::new(1.0, 1.0, 1.0));
+        assert_eq!(*metal.ka(), Vector3::new(0.1, 0.1, 0.1));
+        assert_eq!(*metal.ke(), Vector3::new(0.0, 0.0, 0.5));
+        assert_eq!(metal.pr(), 0.25);
+        assert_eq!(metal.pm(), 1.0);
+        assert!(metal.diffuse_map().is_none());
+    }
+
+    #[test]
+    fn malformed_material() {
+        let e = error("newmtl a\nKd 1 0\n");
+        assert!(matches!(e, ObjError::MissingComponent { .. }), "{:?}", e);
+        assert_eq!(e.line(), Some(2));
+
+        let e = error("newmtl a\nNs 1\nPr high\n");
+        assert!(matches!(e, ObjError::MalformedNumber { .. }), "{:?}", e);
+        assert_eq!(e.line(), Some(3));
+
+        let e = error("newmtl\n");
+        assert_eq!(e.line(), Some(1));
+    }
+}
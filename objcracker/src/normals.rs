@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+use nalgebra::Vector3;
+
+use crate::geometry::corner_angle;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NormalMode {
+    Flat,
+    Smooth,
+}
+
+// 平滑法线中相邻面法线的权重: 面积, 或面片在该顶点处的内角
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NormalWeight {
+    Area,
+    Angle,
+}
+
+// 模型缺少vn时如何生成法线
+// smoothing_groups为true时只在同一平滑组(`s`)的面片间平滑, `s off`/`s 0`的面片使用面法线
+// crease_angle为角度制, 与当前面片法线夹角超过该值的相邻面片不参与平滑, 为None时不限制
+// force为true时忽略文件中的vn, 全部重新生成
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct NormalOptions {
+    pub mode: NormalMode,
+    pub weight: NormalWeight,
+    pub smoothing_groups: bool,
+    pub crease_angle: Option<f32>,
+    pub force: bool,
+}
+
+impl NormalOptions {
+    pub fn new(
+        mode: NormalMode,
+        weight: NormalWeight,
+        smoothing_groups: bool,
+        crease_angle: Option<f32>,
+        force: bool,
+    ) -> NormalOptions {
+        NormalOptions {
+            mode,
+            weight,
+            smoothing_groups,
+            crease_angle,
+            force,
+        }
+    }
+}
+
+impl Default for NormalOptions {
+    fn default() -> NormalOptions {
+        NormalOptions::new(NormalMode::Smooth, NormalWeight::Angle, true, None, false)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 参与法线生成的三角面片: 三个角的位置下标与位置, 以及所属平滑组
+// group为None表示文件中该面片之前没有`s`语句
+pub struct NormalFace {
+    pub v: [usize; 3],
+    pub pos: [Vector3<f32>; 3],
+    pub group: Option<u32>,
+}
+
+// 为每个三角面片的三个角生成单位法线, 相邻关系按位置下标判断
+pub fn generate_normals(faces: &[NormalFace], options: &NormalOptions) -> Vec<[Vector3<f32>; 3]> {
+    // 叉积的模为面积的两倍, 直接作为面积权重
+    let crosses: Vec<Vector3<f32>> = faces
+        .iter()
+        .map(|f| (f.pos[1] - f.pos[0]).cross(&(f.pos[2] - f.pos[0])))
+        .collect();
+    let units: Vec<Vector3<f32>> = crosses
+        .iter()
+        .map(|c| {
+            c.try_normalize(f32::EPSILON)
+                .unwrap_or_else(|| Vector3::new(0.0, 0.0, 1.0))
+        })
+        .collect();
+    if options.mode == NormalMode::Flat {
+        return units.iter().map(|n| [*n; 3]).collect();
+    }
+
+    let mut adjacent: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (iface, face) in faces.iter().enumerate() {
+        for &v in face.v.iter() {
+            let list = adjacent.entry(v).or_default();
+            if list.last() != Some(&iface) {
+                list.push(iface);
+            }
+        }
+    }
+    let min_cos: Option<f32> = options
+        .crease_angle
+        .map(|a| (a.clamp(0.0, 180.0) * std::f32::consts::PI / 180.0).cos());
+    let group = |iface: usize| match options.smoothing_groups {
+        true => faces[iface].group,
+        false => None,
+    };
+
+    faces
+        .iter()
+        .enumerate()
+        .map(|(iface, face)| {
+            let mut normals: [Vector3<f32>; 3] = [units[iface]; 3];
+            if group(iface) == Some(0) {
+                return normals;
+            }
+            for (i, &v) in face.v.iter().enumerate() {
+                let mut sum: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
+                for &other in &adjacent[&v] {
+                    if group(other) != group(iface) {
+                        continue;
+                    }
+                    // 容差避免相同朝向的面片因舍入误差被当作折痕
+                    if let Some(min_cos) = min_cos {
+                        if other != iface && units[iface].dot(&units[other]) < min_cos - 1e-6 {
+                            continue;
+                        }
+                    }
+                    sum += match options.weight {
+                        NormalWeight::Area => crosses[other],
+                        NormalWeight::Angle => {
+                            let corner = faces[other].v.iter().position(|&u| u == v).unwrap();
+                            let pos = &faces[other].pos;
+                            let angle = corner_angle(
+                                &pos[corner],
+                                &pos[(corner + 1) % 3],
+                                &pos[(corner + 2) % 3],
+                            );
+                            units[other] * angle
+                        }
+                    };
+                }
+                if let Some(n) = sum.try_normalize(f32::EPSILON) {
+                    normals[i] = n;
+                }
+            }
+            normals
+        })
+        .collect()
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 边长为2的立方体, 顶点i的xyz分别取i的第0, 1, 2位, 六个面各拆为两个朝外的三角形
+    fn cube(group: Option<u32>) -> Vec<NormalFace> {
+        let pos = |i: usize| {
+            let bit = |b: usize| ((i >> b) & 1) as f32 * 2.0 - 1.0;
+            Vector3::new(bit(0), bit(1), bit(2))
+        };
+        let quads = [
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+        ];
+        let mut faces: Vec<NormalFace> = Vec::new();
+        for q in quads.iter() {
+            for v in [[q[0], q[1], q[2]], [q[0], q[2], q[3]]].iter() {
+                faces.push(NormalFace {
+                    v: *v,
+                    pos: [pos(v[0]), pos(v[1]), pos(v[2])],
+                    group,
+                });
+            }
+        }
+        faces
+    }
+
+    fn options(smoothing_groups: bool, crease_angle: Option<f32>) -> NormalOptions {
+        NormalOptions::new(
+            NormalMode::Smooth,
+            NormalWeight::Angle,
+            smoothing_groups,
+            crease_angle,
+            false,
+        )
+    }
+
+    // 面法线即该面上任意一点坐标中绝对值为1的唯一分量方向
+    fn face_normal(face: &NormalFace) -> Vector3<f32> {
+        let c = (face.pos[0] + face.pos[1] + face.pos[2]) / 3.0;
+        c.map(|x| if x.abs() > 0.99 { x.signum() } else { 0.0 })
+    }
+
+    fn assert_flat(faces: &[NormalFace], normals: &[[Vector3<f32>; 3]]) {
+        for (face, n) in faces.iter().zip(normals.iter()) {
+            for corner in n.iter() {
+                assert!((corner - face_normal(face)).norm() < 1e-5, "{}", corner);
+            }
+        }
+    }
+
+    // 立方体每个顶点处三个面的内角都是90°, 按角度加权后平滑法线沿对角线方向
+    fn assert_smooth(faces: &[NormalFace], normals: &[[Vector3<f32>; 3]]) {
+        for (face, n) in faces.iter().zip(normals.iter()) {
+            for (pos, corner) in face.pos.iter().zip(n.iter()) {
+                assert!((corner - pos.normalize()).norm() < 1e-5, "{}", corner);
+            }
+        }
+    }
+
+    #[test]
+    fn smoothing_off() {
+        let faces = cube(Some(0));
+        assert_flat(&faces, &generate_normals(&faces, &options(true, None)));
+        // 不按平滑组时忽略`s off`
+        assert_smooth(&faces, &generate_normals(&faces, &options(false, None)));
+    }
+
+    #[test]
+    fn smoothing_group() {
+        let faces = cube(Some(1));
+        assert_smooth(&faces, &generate_normals(&faces, &options(true, None)));
+        let faces = cube(None);
+        assert_smooth(&faces, &generate_normals(&faces, &options(true, None)));
+
+        // 不同平滑组的面片互不平滑: 顶点0处-x面单独一组, -y与-z面另为一组
+        let mut faces = cube(Some(1));
+        for face in faces.iter_mut().skip(2) {
+            face.group = Some(2);
+        }
+        let normals = generate_normals(&faces, &options(true, None));
+        assert!((normals[0][0] - Vector3::new(-1.0, 0.0, 0.0)).norm() < 1e-5);
+        assert!((normals[4][0] - Vector3::new(0.0, -1.0, -1.0).normalize()).norm() < 1e-5);
+    }
+
+    #[test]
+    fn flat_mode() {
+        let faces = cube(Some(1));
+        let mut options = options(true, None);
+        options.mode = NormalMode::Flat;
+        assert_flat(&faces, &generate_normals(&faces, &options));
+    }
+
+    #[test]
+    fn crease_angle() {
+        // 相邻面夹角为90°, 阈值以下保留硬边, 同一面上的两个三角形共面始终平滑
+        let faces = cube(Some(1));
+        let normals = |angle: f32| generate_normals(&faces, &options(true, Some(angle)));
+        assert_flat(&faces, &normals(89.0));
+        assert_smooth(&faces, &normals(91.0));
+        assert_flat(&faces, &normals(0.0));
+    }
+}
@@ -1,12 +1,119 @@
 extern crate nalgebra as na;
 
 use std::{
+    collections::HashMap,
     fs::File,
     io::{BufReader, prelude::*},
+    path::Path,
 };
 
-use image::{DynamicImage, GenericImageView};
-use na::{Vector2, Vector3};
+use na::{Vector2, Vector3, Vector4};
+
+use crate::error::ObjError;
+use crate::material::{parse_mtl, Material};
+use crate::normals::{generate_normals, NormalFace, NormalOptions};
+use crate::tangent::{generate_tangents, Corner};
+use crate::triangulate::triangulate;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+pub(crate) fn read_file(file_name: &str) -> Result<String, ObjError> {
+    let io_err = |source| ObjError::Io {
+        file: file_name.to_string(),
+        source,
+    };
+    let file = File::open(file_name).map_err(io_err)?;
+    let mut buf_reader = BufReader::new(file);
+    let mut contents = String::new();
+    buf_reader.read_to_string(&mut contents).map_err(io_err)?;
+    Ok(contents)
+}
+
+// 当前解析行的位置信息, 用于构造错误
+pub(crate) struct LineInfo<'a> {
+    pub(crate) file: &'a str,
+    pub(crate) line: usize,
+    pub(crate) text: &'a str,
+}
+
+impl LineInfo<'_> {
+    pub(crate) fn parse_floats(&self, tokens: &[&str], n: usize) -> Result<Vec<f32>, ObjError> {
+        if tokens.len() < n {
+            return Err(self.missing_component());
+        }
+        tokens[..n]
+            .iter()
+            .map(|t| t.parse::<f32>().map_err(|_| self.malformed_number()))
+            .collect()
+    }
+
+    // 正索引从1开始; 负索引相对于当前已读取的数量, -1 表示最后一个
+    pub(crate) fn parse_index(&self, token: &str, count: usize) -> Result<usize, ObjError> {
+        let index: i64 = token.parse().map_err(|_| self.malformed_number())?;
+        match index {
+            i if i > 0 => Ok(i as usize - 1),
+            i if i < 0 && (-i) as usize <= count => Ok(count - (-i) as usize),
+            i => Err(self.index_out_of_range(i)),
+        }
+    }
+
+    pub(crate) fn malformed_number(&self) -> ObjError {
+        ObjError::MalformedNumber {
+            file: self.file.to_string(),
+            line: self.line,
+            text: self.text.to_string(),
+        }
+    }
+
+    pub(crate) fn missing_component(&self) -> ObjError {
+        ObjError::MissingComponent {
+            file: self.file.to_string(),
+            line: self.line,
+            text: self.text.to_string(),
+        }
+    }
+
+    pub(crate) fn index_out_of_range(&self, index: i64) -> ObjError {
+        ObjError::IndexOutOfRange {
+            file: self.file.to_string(),
+            line: self.line,
+            text: self.text.to_string(),
+            index,
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 面片顶点的 v/vt/vn 索引, 对应 "v", "v/vt", "v//vn", "v/vt/vn" 四种写法
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct FaceVertex {
+    v: usize,
+    vt: Option<usize>,
+    vn: Option<usize>,
+}
+
+impl FaceVertex {
+    // counts为读到该行时v, vt, vn各自的数量, 用于解析负索引
+    fn parse(at: &LineInfo, token: &str, counts: &[usize; 3]) -> Result<FaceVertex, ObjError> {
+        let mut it = token.split('/');
+        let mut idx: [Option<usize>; 3] = [None; 3];
+        for i in 0..3 {
+            idx[i] = match it.next() {
+                Some(s) if !s.is_empty() => Some(at.parse_index(s, counts[i])?),
+                _ => None,
+            };
+        }
+        if it.next().is_some() {
+            return Err(at.malformed_number());
+        }
+        Ok(FaceVertex {
+            v: idx[0].ok_or_else(|| at.missing_component())?,
+            vt: idx[1],
+            vn: idx[2],
+        })
+    }
+}
 
 /////////////////////////////////////////////////////////////////////////////////
 
@@ -15,19 +122,12 @@ pub struct Objcracker {
     verts: Vec<Vector3<f32>>,
     uvs: Vec<Vector2<f32>>,
     normals: Vec<Vector3<f32>>,
-    faces: Vec<Vec<Vector3<usize>>>,
-    diffuse_map: DynamicImage,
-    normal_map: DynamicImage,
-    specular_map: DynamicImage,
-    diff_w: u32,
-    diff_h: u32,
-    norm_w: u32,
-    norm_h: u32,
-    spec_w: u32,
-    spec_h: u32,
-    diffuse_exists: bool,
-    nm_exists: bool,
-    spec_exists: bool,
+    faces: Vec<[FaceVertex; 3]>,
+    tangents: Vec<[Vector4<f32>; 3]>,
+    face_materials: Vec<usize>,
+    npolys_split: usize,
+    materials: Vec<Material>,
+    normal_options: NormalOptions,
 }
 
 impl Objcracker {
@@ -38,128 +138,266 @@ impl Objcracker {
             uvs: Vec::new(),
             normals: Vec::new(),
             faces: Vec::new(),
-            diffuse_map: DynamicImage::new_rgb8(1, 1),
-            normal_map: DynamicImage::new_rgb8(1, 1),
-            specular_map: DynamicImage::new_rgb8(1, 1),
-            diff_w: 0,
-            diff_h: 0,
-            norm_w: 0,
-            norm_h: 0,
-            spec_w: 0,
-            spec_h: 0,
-            diffuse_exists: true,
-            nm_exists: true,
-            spec_exists: true,
-        }
-    }
-
-    pub fn interpret(&mut self) -> std::io::Result<()> {
-        let file = File::open(&format!("{}.obj", self.prefix))?;
-        let mut buf_reader = BufReader::new(file);
-        let mut contents = String::new();
-        buf_reader.read_to_string(&mut contents)?;
-
-        for line in contents.lines() {
-            if line.starts_with("v ") {
-                let tmp: Vec<&str> = line.split_whitespace().collect();
-                let mut v: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
-                v.x = tmp[1].parse().unwrap();
-                v.y = tmp[2].parse().unwrap();
-                v.z = tmp[3].parse().unwrap();
-                self.verts.push(v);
-            } else if line.starts_with("vt") {
-                let tmp: Vec<&str> = line.split_whitespace().collect();
-                let mut vt: Vector2<f32> = Vector2::new(0.0, 0.0);
-                vt.x = tmp[1].parse().unwrap();
-                vt.y = tmp[2].parse().unwrap();
-                self.uvs.push(vt);
-            } else if line.starts_with("vn") {
-                let tmp: Vec<&str> = line.split_whitespace().collect();
-                let mut v: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
-                v.x = tmp[1].parse().unwrap();
-                v.y = tmp[2].parse().unwrap();
-                v.z = tmp[3].parse().unwrap();
-                self.normals.push(v);
-            } else if line.starts_with("f ") {
-                let mut iter = line.split_whitespace();
-                iter.next(); // 跳过"f"
-                let mut f: Vec<Vector3<usize>> = Vec::new();
-                for str in iter {
-                    let mut it = str.split('/');
-                    f.push(Vector3::new(
-                        it.next().unwrap().parse::<usize>().unwrap() - 1,
-                        it.next().unwrap().parse::<usize>().unwrap() - 1,
-                        it.next().unwrap().parse::<usize>().unwrap() - 1,
-                    ));
+            tangents: Vec::new(),
+            face_materials: Vec::new(),
+            npolys_split: 0,
+            materials: Vec::new(),
+            normal_options: NormalOptions::default(),
+        }
+    }
+
+    // 需在interpret之前设置
+    #[inline]
+    pub fn set_normal_options(&mut self, options: NormalOptions) {
+        self.normal_options = options;
+    }
+
+    pub fn interpret(&mut self) -> Result<(), ObjError> {
+        let file_name = format!("{}.obj", self.prefix);
+        let contents = read_file(&file_name)?;
+        self.interpret_contents(&file_name, &contents, parse_mtl)
+    }
+
+    // file_name只用于错误信息与定位材质库, load_mtl按路径读取mtllib引用的材质库
+    fn interpret_contents<F>(
+        &mut self,
+        file_name: &str,
+        contents: &str,
+        load_mtl: F,
+    ) -> Result<(), ObjError>
+    where
+        F: Fn(&str) -> Result<Vec<Material>, ObjError>,
+    {
+        let mut polygons: Vec<Vec<FaceVertex>> = Vec::new();
+        let mut face_lines: Vec<usize> = Vec::new();
+        let mut mtllibs: Vec<String> = Vec::new();
+        let mut usemtl: Option<&str> = None;
+        let mut polygon_mtls: Vec<Option<&str>> = Vec::new();
+        let mut group: Option<u32> = None;
+        let mut polygon_groups: Vec<Option<u32>> = Vec::new();
+        for (iline, line) in contents.lines().enumerate() {
+            let tmp: Vec<&str> = line.split_whitespace().collect();
+            let at = LineInfo {
+                file: file_name,
+                line: iline + 1,
+                text: line,
+            };
+            match tmp.first() {
+                Some(&"v") => {
+                    let v = at.parse_floats(&tmp[1..], 3)?;
+                    self.verts.push(Vector3::new(v[0], v[1], v[2]));
+                }
+                Some(&"vt") => {
+                    let vt = at.parse_floats(&tmp[1..], 2)?;
+                    self.uvs.push(Vector2::new(vt[0], vt[1]));
+                }
+                Some(&"vn") => {
+                    let vn = at.parse_floats(&tmp[1..], 3)?;
+                    self.normals.push(Vector3::new(vn[0], vn[1], vn[2]));
+                }
+                Some(&"f") => {
+                    if tmp.len() < 4 {
+                        return Err(at.missing_component());
+                    }
+                    let counts = [self.verts.len(), self.uvs.len(), self.normals.len()];
+                    let f = tmp[1..]
+                        .iter()
+                        .map(|token| FaceVertex::parse(&at, token, &counts))
+                        .collect::<Result<Vec<FaceVertex>, ObjError>>()?;
+                    polygons.push(f);
+                    face_lines.push(iline);
+                    polygon_mtls.push(usemtl);
+                    polygon_groups.push(group);
+                }
+                Some(&"s") => {
+                    group = match tmp.get(1) {
+                        Some(&"off") => Some(0),
+                        Some(s) => Some(s.parse::<u32>().map_err(|_| at.malformed_number())?),
+                        None => return Err(at.missing_component()),
+                    };
+                }
+                Some(&"mtllib") => {
+                    if tmp.len() < 2 {
+                        return Err(at.missing_component());
+                    }
+                    mtllibs.extend(tmp[1..].iter().map(|s| s.to_string()));
+                }
+                Some(&"usemtl") => {
+                    usemtl = tmp.get(1).map(|_| line.trim()["usemtl".len()..].trim());
                 }
-                self.faces.push(f);
+                _ => (),
             }
         }
 
-        self.open_textures_by_prefix();
+        // 面片可能引用其后定义的顶点, 读取完毕后统一检查索引范围
+        for (iface, face) in polygons.iter().enumerate() {
+            for fv in face {
+                let checks = [
+                    (Some(fv.v), self.verts.len()),
+                    (fv.vt, self.uvs.len()),
+                    (fv.vn, self.normals.len()),
+                ];
+                for (idx, bound) in checks.iter() {
+                    match idx {
+                        Some(i) if i >= bound => {
+                            let iline = face_lines[iface];
+                            let at = LineInfo {
+                                file: file_name,
+                                line: iline + 1,
+                                text: contents.lines().nth(iline).unwrap_or(""),
+                            };
+                            return Err(at.index_out_of_range(*i as i64 + 1));
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+
+        // 0号材质沿用前缀命名约定的贴图, 未指定usemtl的面片使用该材质
+        self.materials.push(Material::from_prefix(&self.prefix));
+        let dir = Path::new(file_name)
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+        for mtllib in &mtllibs {
+            let mtl_name = dir.join(mtllib).display().to_string();
+            match load_mtl(&mtl_name) {
+                Ok(materials) => self.materials.extend(materials),
+                Err(ObjError::Io { .. }) => {
+                    println!("Delusion::Debug::未找到材质库 {}。", mtl_name)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut face_groups: Vec<Option<u32>> = Vec::new();
+        for ((polygon, mtl), group) in polygons
+            .iter()
+            .zip(polygon_mtls.iter())
+            .zip(polygon_groups.iter())
+        {
+            let imtl = match mtl {
+                Some(name) => self.find_material(name).unwrap_or_else(|| {
+                    println!("Delusion::Debug::未定义的材质 {}。", name);
+                    0
+                }),
+                None => 0,
+            };
+            self.push_polygon(polygon, imtl);
+            face_groups.resize(self.faces.len(), *group);
+        }
+        self.fill_normals(&face_groups);
+        self.tangents = self.calc_tangents();
+
         self.print_texture_info();
 
         Ok(())
     }
 
-    fn open_textures_by_prefix(&mut self) {
-        match image::open(&format!("{}_diffuse.tga", self.prefix)) {
-            Ok(image) => self.diffuse_map = image,
-            Err(_) => {
-                println!("Delusion::Debug::未指定漫反射贴图。");
-                self.diffuse_exists = false;
-            }
-        };
-        match image::open(&format!("{}_nm.tga", self.prefix)) {
-            Ok(image) => self.normal_map = image,
-            Err(_) => {
-                println!("Delusion::Debug::未指定法线贴图。");
-                self.diffuse_exists = false;
-            }
-        };
-        match image::open(&format!("{}_spec.tga", self.prefix)) {
-            Ok(image) => self.specular_map = image,
-            Err(_) => {
-                println!("Delusion::Debug::未指定高光贴图。");
-                self.diffuse_exists = false;
+    // 多边形在加载时拆分为三角形, 下游只会看到三角面片
+    fn push_polygon(&mut self, polygon: &[FaceVertex], imtl: usize) {
+        if polygon.len() > 3 {
+            self.npolys_split += 1;
+        }
+        let points: Vec<Vector3<f32>> = polygon.iter().map(|fv| self.verts[fv.v]).collect();
+        for tri in triangulate(&points) {
+            self.faces
+                .push([polygon[tri[0]], polygon[tri[1]], polygon[tri[2]]]);
+            self.face_materials.push(imtl);
+        }
+    }
+
+    // 为缺少vn的角生成法线并追加到法线列表, 相同顶点上相同的法线只保存一份
+    fn fill_normals(&mut self, face_groups: &[Option<u32>]) {
+        let force = self.normal_options.force;
+        let missing = |fv: &FaceVertex| force || fv.vn.is_none();
+        if !self.faces.iter().flatten().any(missing) {
+            return;
+        }
+        let faces: Vec<NormalFace> = self
+            .faces
+            .iter()
+            .zip(face_groups.iter())
+            .map(|(face, group)| NormalFace {
+                v: [face[0].v, face[1].v, face[2].v],
+                pos: [
+                    self.verts[face[0].v],
+                    self.verts[face[1].v],
+                    self.verts[face[2].v],
+                ],
+                group: *group,
+            })
+            .collect();
+        let generated = generate_normals(&faces, &self.normal_options);
+        let mut indices: HashMap<(usize, [u32; 3]), usize> = HashMap::new();
+        for (face, normals) in self.faces.iter_mut().zip(generated.iter()) {
+            for (fv, n) in face.iter_mut().zip(normals.iter()) {
+                if !missing(fv) {
+                    continue;
+                }
+                let key = (fv.v, [n.x.to_bits(), n.y.to_bits(), n.z.to_bits()]);
+                let normals = &mut self.normals;
+                let idx = *indices.entry(key).or_insert_with(|| {
+                    normals.push(*n);
+                    normals.len() - 1
+                });
+                fv.vn = Some(idx);
             }
-        };
-        self.diff_w = self.diffuse_map.width();
-        self.diff_h = self.diffuse_map.height();
-        self.norm_w = self.normal_map.width();
-        self.norm_h = self.normal_map.height();
-        self.spec_w = self.specular_map.width();
-        self.spec_h = self.specular_map.height();
+        }
+    }
+
+    // fill_normals之后每个角都有vn, 相同(v, vt, vn)的角共享切线
+    fn calc_tangents(&self) -> Vec<[Vector4<f32>; 3]> {
+        let corners: Vec<[Corner<FaceVertex>; 3]> = (0..self.nfaces())
+            .map(|iface| {
+                let corner = |ivert: usize| {
+                    let fv = self.faces[iface][ivert];
+                    Corner {
+                        pos: self.calc_vert(iface, ivert),
+                        uv: self.calc_uv(iface, ivert),
+                        normal: self.calc_normal(iface, ivert),
+                        key: fv,
+                    }
+                };
+                [corner(0), corner(1), corner(2)]
+            })
+            .collect();
+        generate_tangents(&corners)
+    }
+
+    fn find_material(&self, name: &str) -> Option<usize> {
+        self.materials.iter().position(|m| m.name() == name)
     }
 
     fn print_texture_info(&self) {
         println!("*****Delusion::Debug**************");
-        println!("obj_cracker::模型::{}", format!("{}.obj", self.prefix));
+        println!("obj_cracker::模型::{}.obj", self.prefix);
         println!("obj_cracker::顶点::{}", self.nverts());
         println!("obj_cracker::纹理::{}", self.nuvs());
         println!("obj_cracker::法线::{}", self.nnormals());
-        println!("obj_cracker::面片::{}\n", self.nfaces());
-        println!(
-            "obj_cracker::纹理::{}",
-            format!("{}_diffuse.tga", self.prefix)
-        );
-        println!(
-            "obj_cracker::通道::{}",
-            format!("{:?}", self.diffuse_map.color())
-        );
-        println!("obj_cracker::分辨率::{:?}", self.diffuse_map.dimensions());
-        println!("obj_cracker::法线::{}", format!("{}_nm.tga", self.prefix));
-        println!(
-            "obj_cracker::通道::{}",
-            format!("{:?}", self.normal_map.color())
-        );
-        println!("obj_cracker::分辨率::{:?}", self.normal_map.dimensions());
-        println!("obj_cracker::高光::{}", format!("{}_spec.tga", self.prefix));
-        println!(
-            "obj_cracker::通道::{}",
-            format!("{:?}", self.specular_map.color())
-        );
-        println!("obj_cracker::分辨率::{:?}", self.specular_map.dimensions());
+        println!("obj_cracker::面片::{}", self.nfaces());
+        println!("obj_cracker::拆分多边形::{}", self.npolys_split());
+        println!("obj_cracker::材质::{}\n", self.nmaterials());
+        for material in &self.materials {
+            println!("obj_cracker::材质::{}", material.name());
+            let maps = [
+                ("纹理", material.diffuse_map()),
+                ("法线", material.normal_map()),
+                ("高光", material.specular_map()),
+                ("粗糙度", material.roughness_map()),
+                ("金属度", material.metallic_map()),
+                ("金属度粗糙度", material.metallic_roughness_map()),
+                ("环境光遮蔽", material.occlusion_map()),
+                ("自发光", material.emissive_map()),
+            ];
+            for (kind, map) in maps.iter() {
+                if let Some(texture) = map {
+                    println!("obj_cracker::{}::{}", kind, texture.path());
+                    println!("obj_cracker::通道::{:?}", texture.color());
+                    println!("obj_cracker::分辨率::{:?}", texture.dimensions());
+                }
+            }
+        }
         println!("**********************************\n");
     }
 
@@ -170,67 +408,72 @@ impl Objcracker {
     }
 
     pub fn calc_vert(&self, iface: usize, ivert: usize) -> Vector3<f32> {
-        let vt_idx = self.faces[iface][ivert][0] as usize;
-        self.verts[vt_idx]
+        self.verts[self.faces[iface][ivert].v]
     }
 
+    // 缺少vt时返回(0,0)
     pub fn calc_uv(&self, iface: usize, ivert: usize) -> Vector2<f32> {
-        self.uvs[self.faces[iface][ivert][1]]
+        match self.faces[iface][ivert].vt {
+            Some(vt_idx) => self.uvs[vt_idx],
+            None => Vector2::new(0.0, 0.0),
+        }
     }
 
+    // 缺少vn时以面法线代替
     pub fn calc_normal(&self, iface: usize, ivert: usize) -> Vector3<f32> {
-        let vn_idx = self.faces[iface][ivert][2] as usize;
-        self.normals[vn_idx].normalize()
+        match self.faces[iface][ivert].vn {
+            Some(vn_idx) => self.normals[vn_idx].normalize(),
+            None => self.face_normal(iface),
+        }
+    }
+
+    // xyz为模型空间中的单位切线, w为副切线的符号(±1)
+    #[inline]
+    pub fn calc_tangent(&self, iface: usize, ivert: usize) -> Vector4<f32> {
+        self.tangents[iface][ivert]
+    }
+
+    pub fn calc_bitangent(&self, iface: usize, ivert: usize) -> Vector3<f32> {
+        let t = self.calc_tangent(iface, ivert);
+        self.calc_normal(iface, ivert).cross(&t.xyz()) * t.w
+    }
+
+    pub fn face_normal(&self, iface: usize) -> Vector3<f32> {
+        let a = self.calc_vert(iface, 0);
+        let b = self.calc_vert(iface, 1);
+        let c = self.calc_vert(iface, 2);
+        let n = (b - a).cross(&(c - a));
+        match n.norm() {
+            len if len > 0.0 => n / len,
+            _ => Vector3::new(0.0, 0.0, 1.0),
+        }
     }
 
     pub fn face(&self, idx: usize) -> Vector3<usize> {
         let mut face: Vector3<usize> = Vector3::new(0, 0, 0);
         for i in 0..3 {
-            face[i] = self.faces[idx][i][0] as usize;
+            face[i] = self.faces[idx][i].v;
         }
         face
     }
 
     /////////////////////////////////////////////////////////////////////////////////
 
-    pub fn diffuse(&self, uv: &Vector2<f32>) -> Vector3<f32> {
-        let x = (self.diff_w as f32 * uv.x) as u32;
-        let y = self.diff_h - 1 - (self.diff_h as f32 * uv.y) as u32;
-        if x >= self.diff_w || y >= self.diff_h {
-            return Vector3::new(79.0, 147.0, 184.0);
-        }
-        let color = self.diffuse_map.get_pixel(x, y);
-        Vector3::new(color[0] as f32, color[1] as f32, color[2] as f32)
-    }
-
-    pub fn normal(&self, uv: &Vector2<f32>) -> Vector3<f32> {
-        let x = (self.norm_w as f32 * uv.x) as u32;
-        let y = self.norm_h - 1 - (self.norm_h as f32 * uv.y) as u32;
-        if x >= self.diff_w || y >= self.diff_h {
-            return Vector3::new(0.0, 0.0, 0.0);
-        }
-        let color = self.normal_map.get_pixel(x, y);
-        let mut res: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
-        for i in 0..3 {
-            res[i] = color[i] as f32 / 255.0 * 2.0 - 1.0;
-        }
-        res
+    #[inline]
+    pub fn material(&self, iface: usize) -> &Material {
+        &self.materials[self.face_materials[iface]]
     }
 
-    pub fn specular(&self, uv: &Vector2<f32>) -> f32 {
-        let x = (self.spec_w as f32 * uv.x) as u32;
-        let y = self.spec_h - 1 - (self.spec_h as f32 * uv.y) as u32;
-        if x >= self.diff_w || y >= self.diff_h {
-            return 0.0;
-        }
-        self.specular_map.get_pixel(x, y)[0] as f32 / 1.0
+    #[inline]
+    pub fn materials(&self) -> &[Material] {
+        &self.materials
     }
 
     /////////////////////////////////////////////////////////////////////////////////
 
     #[inline]
     pub fn texture_status(&self) -> [bool; 3] {
-        [self.diffuse_exists, self.nm_exists, self.spec_exists]
+        self.materials[0].texture_status()
     }
     #[inline]
     pub fn nverts(&self) -> usize {
@@ -247,8 +490,212 @@ impl Objcracker {
         self.faces.len()
     }
 
+    #[inline]
+    pub fn npolys_split(&self) -> usize {
+        self.npolys_split
+    }
+
+    #[inline]
+    pub fn nmaterials(&self) -> usize {
+        self.materials.len()
+    }
+
     #[inline]
     pub fn nuvs(&self) -> usize {
         self.uvs.len()
     }
 }
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::parse_mtl_contents;
+
+    fn parse(contents: &str) -> Result<Objcracker, ObjError> {
+        let mut obj = Objcracker::new("/nonexistent/test");
+        obj.interpret_contents("test.obj", contents, parse_mtl)?;
+        Ok(obj)
+    }
+
+    fn error(contents: &str) -> ObjError {
+        match parse(contents) {
+            Ok(_) => panic!("expected an error for {:?}", contents),
+            Err(e) => e,
+        }
+    }
+
+    fn out_of_range(e: &ObjError) -> Option<i64> {
+        match e {
+            ObjError::IndexOutOfRange { index, .. } => Some(*index),
+            _ => None,
+        }
+    }
+
+    fn fv(v: usize, vt: Option<usize>, vn: Option<usize>) -> FaceVertex {
+        FaceVertex { v, vt, vn }
+    }
+
+    #[test]
+    fn face_vertex_forms() {
+        let at = LineInfo {
+            file: "test.obj",
+            line: 1,
+            text: "",
+        };
+        let counts = [4, 3, 2];
+        let parse = |token| FaceVertex::parse(&at, token, &counts).unwrap();
+        assert_eq!(parse("2"), fv(1, None, None));
+        assert_eq!(parse("2/3"), fv(1, Some(2), None));
+        assert_eq!(parse("2//1"), fv(1, None, Some(0)));
+        assert_eq!(parse("4/1/2"), fv(3, Some(0), Some(1)));
+        assert_eq!(parse("2/"), fv(1, None, None));
+
+        // 负索引相对于已读取的数量, 各分量分别计数
+        assert_eq!(parse("-1"), fv(3, None, None));
+        assert_eq!(parse("-4/-3/-2"), fv(0, Some(0), Some(0)));
+        assert_eq!(parse("-1//-1"), fv(3, None, Some(1)));
+        assert!(FaceVertex::parse(&at, "-5", &counts).is_err());
+        assert!(FaceVertex::parse(&at, "1/-4", &counts).is_err());
+    }
+
+    #[test]
+    fn negative_index_after_append() {
+        // 同一个"f -3 -2 -1"在追加顶点后指向新的顶点
+        let obj = parse(
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nf -3/-1 -2/-1 -1/-1\n\
+             v 0 0 1\nv 1 0 1\nv 0 1 1\nvt 1 1\nf -3/-1 -2/-1 -1/-1\n\
+             f 1/1 -5/2 -1/-2\n",
+        )
+        .unwrap();
+        assert_eq!(obj.nfaces(), 3);
+        assert_eq!(obj.face(0), Vector3::new(0, 1, 2));
+        assert_eq!(obj.face(1), Vector3::new(3, 4, 5));
+        assert_eq!(obj.face(2), Vector3::new(0, 1, 5));
+        assert_eq!(obj.faces[0][0].vt, Some(0));
+        assert_eq!(obj.faces[1][0].vt, Some(1));
+        assert_eq!(obj.faces[2][1].vt, Some(1));
+        assert_eq!(obj.faces[2][2].vt, Some(0));
+        assert_eq!(obj.calc_vert(1, 2), Vector3::new(0.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn face_materials() {
+        let mtl = "newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\n";
+        let mut obj = Objcracker::new("/nonexistent/test");
+        obj.interpret_contents(
+            "models/test.obj",
+            "mtllib lib.mtl\n\
+             v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+             f 1 2 3\n\
+             usemtl blue\n\
+             f 1 2 3 4\n\
+             usemtl red\n\
+             f 1 3 4\n\
+             usemtl green\n\
+             f 1 2 3\n\
+             usemtl blue\n\
+             f 1 2 4\n",
+            |name| {
+                assert_eq!(Path::new(name), Path::new("models").join("lib.mtl"));
+                parse_mtl_contents(name, mtl)
+            },
+        )
+        .unwrap();
+        assert_eq!(obj.nmaterials(), 3);
+
+        // 四边形拆成两个三角形, 都沿用blue; 未使用usemtl或材质未定义的面片使用0号材质
+        let names: Vec<&str> = (0..obj.nfaces()).map(|i| obj.material(i).name()).collect();
+        let expected = [
+            "/nonexistent/test",
+            "blue",
+            "blue",
+            "red",
+            "/nonexistent/test",
+            "blue",
+        ];
+        assert_eq!(names, expected);
+        assert_eq!(*obj.material(3).kd(), Vector3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn cube_smoothing_statements() {
+        let cube = "v -1 -1 -1\nv 1 -1 -1\nv -1 1 -1\nv 1 1 -1\n\
+                    v -1 -1 1\nv 1 -1 1\nv -1 1 1\nv 1 1 1\n\
+                    f 1 5 7 3\nf 2 4 8 6\nf 1 2 6 5\nf 3 7 8 4\nf 1 3 4 2\nf 5 6 8 7\n";
+        // 硬边时每个顶点上三个面的法线各存一份, 平滑时每个顶点一份
+        let obj = parse(&format!("s off\n{}", cube)).unwrap();
+        assert_eq!(obj.nnormals(), 24);
+        assert_eq!(obj.calc_normal(0, 0), Vector3::new(-1.0, 0.0, 0.0));
+        let obj = parse(&format!("s 1\n{}", cube)).unwrap();
+        assert_eq!(obj.nnormals(), 8);
+        let diagonal = Vector3::new(-1.0, -1.0, -1.0).normalize();
+        assert!((obj.calc_normal(0, 0) - diagonal).norm() < 1e-5);
+    }
+
+    #[test]
+    fn malformed_vertex() {
+        let e = error("v 0 0 0\nv 1 x 0\n");
+        assert!(matches!(e, ObjError::MalformedNumber { .. }), "{:?}", e);
+        assert_eq!(e.line(), Some(2));
+        assert_eq!(e.file(), "test.obj");
+
+        let e = error("# comment\n\nv 1 2\n");
+        assert!(matches!(e, ObjError::MissingComponent { .. }), "{:?}", e);
+        assert_eq!(e.line(), Some(3));
+        assert_eq!(e.to_string(), "test.obj:3: missing component in `v 1 2`");
+    }
+
+    #[test]
+    fn malformed_uv() {
+        let e = error("v 0 0 0\nvt 0.5\n");
+        assert!(matches!(e, ObjError::MissingComponent { .. }), "{:?}", e);
+        assert_eq!(e.line(), Some(2));
+
+        let e = error("vt 0 0\nvt 0 0\nvt 0,5 1\n");
+        assert!(matches!(e, ObjError::MalformedNumber { .. }), "{:?}", e);
+        assert_eq!(e.line(), Some(3));
+    }
+
+    #[test]
+    fn malformed_face() {
+        let verts = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
+
+        let e = error(&format!("{}f 1 2\n", verts));
+        assert!(matches!(e, ObjError::MissingComponent { .. }), "{:?}", e);
+        assert_eq!(e.line(), Some(4));
+
+        let e = error(&format!("{}f 1 two 3\n", verts));
+        assert!(matches!(e, ObjError::MalformedNumber { .. }), "{:?}", e);
+        assert_eq!(e.line(), Some(4));
+
+        let e = error(&format!("{}f 1/1/1/1 2 3\n", verts));
+        assert!(matches!(e, ObjError::MalformedNumber { .. }), "{:?}", e);
+
+        let e = error(&format!("{}f /1 2 3\n", verts));
+        assert!(matches!(e, ObjError::MissingComponent { .. }), "{:?}", e);
+
+        let e = error(&format!("{}f 0 1 2\n", verts));
+        assert_eq!(out_of_range(&e), Some(0));
+
+        let e = error(&format!("{}f 1 2 -4\n", verts));
+        assert_eq!(out_of_range(&e), Some(-4));
+    }
+
+    #[test]
+    fn index_past_end() {
+        // 越界的正索引在读完全部顶点后才报告, 行号仍指向面片所在行
+        let e = error("v 0 0 0\nv 1 0 0\nf 1 2 3\nv 0 1 0\nf 1 2 4\nvt 0 0\n");
+        assert_eq!(out_of_range(&e), Some(4));
+        assert_eq!(e.line(), Some(5));
+        assert_eq!(
+            e.to_string(),
+            "test.obj:5: index 4 out of range in `f 1 2 4`"
+        );
+
+        let e = error("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nf 1/1 2/2 3/1\n");
+        assert_eq!(out_of_range(&e), Some(2));
+        assert_eq!(e.line(), Some(5));
+    }
+}
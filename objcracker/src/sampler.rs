@@ -0,0 +1,160 @@
+use std::fmt::{Display, Error, Formatter};
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::texture::Texture;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+    Trilinear,
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Filter::Nearest => write!(f, "Nearest"),
+            Filter::Bilinear => write!(f, "Bilinear"),
+            Filter::Trilinear => write!(f, "Trilinear"),
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 纹理寻址模式, 决定越界纹素坐标如何映射回纹理
+#[derive(Clone, Copy, PartialEq)]
+pub enum AddressMode {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder(Vector3<f32>),
+}
+
+impl AddressMode {
+    // 将一维纹素坐标映射到[0, n), 边框颜色由调用方处理
+    pub fn wrap(&self, i: i64, n: i64) -> i64 {
+        match self {
+            AddressMode::Repeat => i.rem_euclid(n),
+            AddressMode::MirroredRepeat => {
+                let k = i.rem_euclid(2 * n);
+                match k >= n {
+                    true => 2 * n - 1 - k,
+                    false => k,
+                }
+            }
+            AddressMode::ClampToEdge | AddressMode::ClampToBorder(_) => i.clamp(0, n - 1),
+        }
+    }
+}
+
+impl Display for AddressMode {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            AddressMode::Repeat => write!(f, "Repeat"),
+            AddressMode::MirroredRepeat => write!(f, "MirroredRepeat"),
+            AddressMode::ClampToEdge => write!(f, "ClampToEdge"),
+            AddressMode::ClampToBorder(c) => write!(f, "ClampToBorder({}, {}, {})", c.x, c.y, c.z),
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 纹理坐标及其屏幕空间偏导, 偏导为零时只采样第0级
+#[derive(Clone, Copy, Default)]
+pub struct TexCoord {
+    pub uv: Vector2<f32>,
+    pub ddx: Vector2<f32>,
+    pub ddy: Vector2<f32>,
+}
+
+impl TexCoord {
+    pub fn new(uv: Vector2<f32>, ddx: Vector2<f32>, ddy: Vector2<f32>) -> TexCoord {
+        TexCoord { uv, ddx, ddy }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy)]
+pub struct Sampler {
+    filter: Filter,
+}
+
+impl Sampler {
+    pub fn new(filter: Filter) -> Sampler {
+        Sampler { filter }
+    }
+
+    // 越界的uv按纹理的寻址模式处理
+    pub fn sample(&self, texture: &Texture, tc: &TexCoord) -> Vector3<f32> {
+        let lod = self.lod(texture, tc);
+        match self.filter {
+            Filter::Nearest => nearest(texture, lod.round() as usize, &tc.uv),
+            Filter::Bilinear => bilinear(texture, lod.round() as usize, &tc.uv),
+            Filter::Trilinear => {
+                let (lo, t) = (lod.floor() as usize, lod.fract());
+                let hi = (lo + 1).min(texture.levels() - 1);
+                bilinear(texture, lo, &tc.uv) * (1.0 - t) + bilinear(texture, hi, &tc.uv) * t
+            }
+        }
+    }
+
+    // 由uv偏导在第0级纹素空间中的最大跨度选择mip级别
+    fn lod(&self, texture: &Texture, tc: &TexCoord) -> f32 {
+        let (w, h) = texture.dimensions();
+        let size = Vector2::new(w as f32, h as f32);
+        let rho = tc
+            .ddx
+            .component_mul(&size)
+            .norm()
+            .max(tc.ddy.component_mul(&size).norm());
+        if !rho.is_finite() || rho <= 1.0 {
+            return 0.0;
+        }
+        rho.log2().min((texture.levels() - 1) as f32)
+    }
+
+    #[inline]
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+}
+
+impl Default for Sampler {
+    fn default() -> Sampler {
+        Sampler::new(Filter::Trilinear)
+    }
+}
+
+impl Display for Sampler {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.filter)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 纹素行号自上而下, 而v轴向上
+fn nearest(texture: &Texture, level: usize, uv: &Vector2<f32>) -> Vector3<f32> {
+    let (w, h) = texture.level_dimensions(level);
+    let x = (w as f32 * uv.x).floor() as i64;
+    let y = h as i64 - 1 - (h as f32 * uv.y).floor() as i64;
+    texture.address(level, x, y)
+}
+
+fn bilinear(texture: &Texture, level: usize, uv: &Vector2<f32>) -> Vector3<f32> {
+    let (w, h) = texture.level_dimensions(level);
+    let fx = uv.x * w as f32 - 0.5;
+    let fy = uv.y * h as f32 - 0.5;
+    let (x0, y0) = (fx.floor(), fy.floor());
+    let (tx, ty) = (fx - x0, fy - y0);
+    let texel = |x: f32, y: f32| texture.address(level, x as i64, h as i64 - 1 - y as i64);
+    let bottom = texel(x0, y0) * (1.0 - tx) + texel(x0 + 1.0, y0) * tx;
+    let top = texel(x0, y0 + 1.0) * (1.0 - tx) + texel(x0 + 1.0, y0 + 1.0) * tx;
+    bottom * (1.0 - ty) + top * ty
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use nalgebra::{Vector2, Vector3, Vector4};
+
+use crate::geometry::corner_angle;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 三角形的一个角: 位置, uv, 单位法线, 以及用于合并共享顶点的索引键
+pub struct Corner<K> {
+    pub pos: Vector3<f32>,
+    pub uv: Vector2<f32>,
+    pub normal: Vector3<f32>,
+    pub key: K,
+}
+
+// 按MikkTSpace的约定为每个三角形的角生成切线:
+// 三角形的uv切线先投影到角的法线平面, 按角度加权累加到位置, uv, 法线与朝向都相同的角上,
+// 再对法线做Gram-Schmidt正交化. w为副切线的符号, 副切线 = w * cross(n, t)
+pub fn generate_tangents<K>(faces: &[[Corner<K>; 3]]) -> Vec<[Vector4<f32>; 3]>
+where
+    K: Copy + Eq + std::hash::Hash,
+{
+    let mut sums: HashMap<(K, bool), Vector3<f32>> = HashMap::new();
+    let mut face_signs: Vec<bool> = Vec::with_capacity(faces.len());
+    for face in faces {
+        let (sdir, positive) = face_tangent(face);
+        face_signs.push(positive);
+        for i in 0..3 {
+            let corner = &face[i];
+            let t = sdir - corner.normal * corner.normal.dot(&sdir);
+            let len = t.norm();
+            if !sdir.iter().all(|x| x.is_finite()) || len <= f32::EPSILON {
+                continue;
+            }
+            let weight = corner_angle(&face[i].pos, &face[(i + 1) % 3].pos, &face[(i + 2) % 3].pos);
+            *sums
+                .entry((corner.key, positive))
+                .or_insert_with(|| Vector3::new(0.0, 0.0, 0.0)) += t / len * weight;
+        }
+    }
+
+    faces
+        .iter()
+        .zip(face_signs.iter())
+        .map(|(face, &positive)| {
+            let sign: f32 = match positive {
+                true => 1.0,
+                false => -1.0,
+            };
+            let mut tangents: [Vector4<f32>; 3] = Default::default();
+            for i in 0..3 {
+                let n = face[i].normal;
+                let sum = sums
+                    .get(&(face[i].key, positive))
+                    .copied()
+                    .unwrap_or_else(|| Vector3::new(0.0, 0.0, 0.0));
+                let t = match (sum - n * n.dot(&sum)).try_normalize(f32::EPSILON) {
+                    Some(t) => t,
+                    None => any_perpendicular(&n),
+                };
+                tangents[i] = Vector4::new(t.x, t.y, t.z, sign);
+            }
+            tangents
+        })
+        .collect()
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 由边与uv差解出u方向在模型空间中的方向, 第二个返回值表示uv映射是否保持朝向
+fn face_tangent<K>(face: &[Corner<K>; 3]) -> (Vector3<f32>, bool) {
+    let e1 = face[1].pos - face[0].pos;
+    let e2 = face[2].pos - face[0].pos;
+    let d1 = face[1].uv - face[0].uv;
+    let d2 = face[2].uv - face[0].uv;
+    let det = d1.x * d2.y - d2.x * d1.y;
+    let sdir = (e1 * d2.y - e2 * d1.y) / det;
+    (sdir, det >= 0.0)
+}
+
+// uv退化时任取一个与法线垂直的方向
+fn any_perpendicular(n: &Vector3<f32>) -> Vector3<f32> {
+    let axis = match n.x.abs() < 0.9 {
+        true => Vector3::new(1.0, 0.0, 0.0),
+        false => Vector3::new(0.0, 1.0, 0.0),
+    };
+    (axis - n * n.dot(&axis))
+        .try_normalize(f32::EPSILON)
+        .unwrap_or(axis)
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 3x2个顶点组成的两个四边形, 右侧四边形的u沿x镜像, 接缝x=1处的顶点两侧uv相同
+    fn mirrored_quads() -> Vec<[Corner<usize>; 3]> {
+        let corner = |i: usize| {
+            let (x, y) = ((i % 3) as f32, (i / 3) as f32);
+            let u = if x <= 1.0 { x } else { 2.0 - x };
+            Corner {
+                pos: Vector3::new(x, y, 0.0),
+                uv: Vector2::new(u, y),
+                normal: Vector3::new(0.0, 0.0, 1.0),
+                key: i,
+            }
+        };
+        [[0, 1, 4], [0, 4, 3], [1, 2, 5], [1, 5, 4]]
+            .iter()
+            .map(|f| [corner(f[0]), corner(f[1]), corner(f[2])])
+            .collect()
+    }
+
+    #[test]
+    fn uv_mirrored_quad() {
+        let faces = mirrored_quads();
+        let tangents = generate_tangents(&faces);
+        assert_eq!(tangents.len(), 4);
+        for (iface, face) in tangents.iter().enumerate() {
+            // 镜像一侧的切线反向, 符号为负; 接缝上的角不与另一侧合并
+            let expected = match iface < 2 {
+                true => Vector4::new(1.0, 0.0, 0.0, 1.0),
+                false => Vector4::new(-1.0, 0.0, 0.0, -1.0),
+            };
+            for (i, t) in face.iter().enumerate() {
+                assert!((t - expected).norm() < 1e-5, "{}: {}", iface, t);
+                // 两侧的副切线都沿v增大的方向
+                let b = faces[iface][i].normal.cross(&t.xyz()) * t.w;
+                assert!((b - Vector3::new(0.0, 1.0, 0.0)).norm() < 1e-5);
+            }
+        }
+    }
+}
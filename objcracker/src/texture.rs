@@ -0,0 +1,170 @@
+use std::path::Path;
+
+use image::{ColorType, GenericImageView, ImageResult};
+use nalgebra::Vector3;
+
+use crate::sampler::AddressMode;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 纹理文件中颜色的编码: 颜色类贴图按sRGB存储, 法线, 粗糙度等数据贴图为线性值
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorSpace {
+    Linear,
+    Srgb,
+}
+
+// sRGB传递函数, 输入输出均取0~1
+pub fn srgb_to_linear(c: f32) -> f32 {
+    match c <= 0.04045 {
+        true => c / 12.92,
+        false => ((c + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    match c <= 0.0031308 {
+        true => c * 12.92,
+        false => 1.055 * c.powf(1.0 / 2.4) - 0.055,
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 单级纹理, 按图像行序(自上而下)存储
+struct MipLevel {
+    w: u32,
+    h: u32,
+    texels: Vec<Vector3<f32>>,
+}
+
+impl MipLevel {
+    // 2x2盒式滤波得到下一级, 奇数边长时边缘纹素重复使用
+    fn downsample(&self) -> MipLevel {
+        let (w, h) = ((self.w / 2).max(1), (self.h / 2).max(1));
+        let mut texels: Vec<Vector3<f32>> = Vec::with_capacity((w * h) as usize);
+        for y in 0..h {
+            for x in 0..w {
+                let (x0, y0) = (2 * x, 2 * y);
+                let (x1, y1) = ((x0 + 1).min(self.w - 1), (y0 + 1).min(self.h - 1));
+                let sum = self.texel(x0, y0)
+                    + self.texel(x1, y0)
+                    + self.texel(x0, y1)
+                    + self.texel(x1, y1);
+                texels.push(sum / 4.0);
+            }
+        }
+        MipLevel { w, h, texels }
+    }
+
+    #[inline]
+    fn texel(&self, x: u32, y: u32) -> Vector3<f32> {
+        self.texels[(x + y * self.w) as usize]
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+pub struct Texture {
+    path: String,
+    color: ColorType,
+    space: ColorSpace,
+    mips: Vec<MipLevel>,
+    address: AddressMode,
+}
+
+impl Texture {
+    pub fn open<P: AsRef<Path>>(path: P) -> ImageResult<Texture> {
+        Texture::open_as(path, ColorSpace::Linear)
+    }
+
+    // 载入时生成完整的mip链, 直至1x1
+    // sRGB贴图在此解码到线性空间(仍取0~255), 采样与mip滤波均在线性空间中进行
+    pub fn open_as<P: AsRef<Path>>(path: P, space: ColorSpace) -> ImageResult<Texture> {
+        let image = image::open(&path)?;
+        let (w, h) = image.dimensions();
+        let mut texels: Vec<Vector3<f32>> = Vec::with_capacity((w * h) as usize);
+        for y in 0..h {
+            for x in 0..w {
+                let color = image.get_pixel(x, y);
+                let texel = Vector3::new(color[0] as f32, color[1] as f32, color[2] as f32);
+                texels.push(match space {
+                    ColorSpace::Linear => texel,
+                    ColorSpace::Srgb => texel.map(|c| srgb_to_linear(c / 255.0) * 255.0),
+                });
+            }
+        }
+        let mut mips = vec![MipLevel { w, h, texels }];
+        while let Some(last) = mips.last() {
+            if last.w == 1 && last.h == 1 {
+                break;
+            }
+            let next = last.downsample();
+            mips.push(next);
+        }
+        Ok(Texture {
+            path: path.as_ref().display().to_string(),
+            color: image.color(),
+            space,
+            mips,
+            address: AddressMode::Repeat,
+        })
+    }
+
+    // 第level级的纹素, y按图像行序自上而下, 坐标需在范围内
+    #[inline]
+    pub fn texel(&self, level: usize, x: u32, y: u32) -> Vector3<f32> {
+        self.mips[level].texel(x, y)
+    }
+
+    // 按寻址模式取纹素, 坐标可越界, y按图像行序自上而下
+    pub fn address(&self, level: usize, x: i64, y: i64) -> Vector3<f32> {
+        let (w, h) = self.level_dimensions(level);
+        let (w, h) = (w as i64, h as i64);
+        if let AddressMode::ClampToBorder(color) = self.address {
+            if x < 0 || y < 0 || x >= w || y >= h {
+                return color;
+            }
+        }
+        self.texel(
+            level,
+            self.address.wrap(x, w) as u32,
+            self.address.wrap(y, h) as u32,
+        )
+    }
+
+    /////////////////////////////////////////////////////////////////////////////////
+
+    #[inline]
+    pub fn set_address_mode(&mut self, address: AddressMode) {
+        self.address = address;
+    }
+    #[inline]
+    pub fn address_mode(&self) -> AddressMode {
+        self.address
+    }
+    #[inline]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    #[inline]
+    pub fn color(&self) -> ColorType {
+        self.color
+    }
+    #[inline]
+    pub fn color_space(&self) -> ColorSpace {
+        self.space
+    }
+    #[inline]
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.level_dimensions(0)
+    }
+    #[inline]
+    pub fn levels(&self) -> usize {
+        self.mips.len()
+    }
+    #[inline]
+    pub fn level_dimensions(&self, level: usize) -> (u32, u32) {
+        (self.mips[level].w, self.mips[level].h)
+    }
+}
@@ -0,0 +1,227 @@
+use nalgebra::{Vector2, Vector3};
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 将多边形拆分为三角形, 返回多边形内的顶点序号
+// 凸多边形使用扇形剖分, 凹多边形使用耳切法
+pub fn triangulate(points: &[Vector3<f32>]) -> Vec<[usize; 3]> {
+    let n = points.len();
+    if n < 3 {
+        return Vec::new();
+    }
+    if n == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    let poly = project_ccw(points);
+    match poly {
+        Some(ref p) if !is_convex(p) => ear_clip(p),
+        _ => fan(n),
+    }
+}
+
+fn fan(n: usize) -> Vec<[usize; 3]> {
+    (1..n - 1).map(|i| [0, i, i + 1]).collect()
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// Newell法求多边形法线, 投影到法线主分量所在平面, 并保证结果为逆时针
+fn project_ccw(points: &[Vector3<f32>]) -> Option<Vec<Vector2<f32>>> {
+    let n = points.len();
+    let mut normal: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
+    for i in 0..n {
+        let a = points[i];
+        let b = points[(i + 1) % n];
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+    let (ax, ay, az) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+    if ax.max(ay).max(az) <= f32::EPSILON {
+        return None;
+    }
+
+    // 丢弃主分量后 (u,v) 构成右手系时多边形为逆时针
+    let (u, v, flip) = if az >= ax && az >= ay {
+        (0, 1, normal.z < 0.0)
+    } else if ax >= ay {
+        (1, 2, normal.x < 0.0)
+    } else {
+        (2, 0, normal.y < 0.0)
+    };
+    Some(
+        points
+            .iter()
+            .map(|p| match flip {
+                true => Vector2::new(p[v], p[u]),
+                false => Vector2::new(p[u], p[v]),
+            })
+            .collect(),
+    )
+}
+
+#[inline]
+fn cross2(o: &Vector2<f32>, a: &Vector2<f32>, b: &Vector2<f32>) -> f32 {
+    (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+}
+
+fn is_convex(poly: &[Vector2<f32>]) -> bool {
+    let n = poly.len();
+    (0..n).all(|i| cross2(&poly[i], &poly[(i + 1) % n], &poly[(i + 2) % n]) >= 0.0)
+}
+
+fn in_triangle(p: &Vector2<f32>, a: &Vector2<f32>, b: &Vector2<f32>, c: &Vector2<f32>) -> bool {
+    cross2(a, b, p) >= 0.0 && cross2(b, c, p) >= 0.0 && cross2(c, a, p) >= 0.0
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+fn ear_clip(poly: &[Vector2<f32>]) -> Vec<[usize; 3]> {
+    let mut remain: Vec<usize> = (0..poly.len()).collect();
+    let mut tris: Vec<[usize; 3]> = Vec::with_capacity(poly.len() - 2);
+
+    while remain.len() > 3 {
+        let m = remain.len();
+        let ear = (0..m).find(|&i| {
+            let (ia, ib, ic) = (remain[(i + m - 1) % m], remain[i], remain[(i + 1) % m]);
+            let (a, b, c) = (&poly[ia], &poly[ib], &poly[ic]);
+            if cross2(a, b, c) <= 0.0 {
+                return false;
+            }
+            remain
+                .iter()
+                .filter(|&&j| j != ia && j != ib && j != ic)
+                .all(|&j| !in_triangle(&poly[j], a, b, c))
+        });
+        match ear {
+            Some(i) => {
+                tris.push([remain[(i + m - 1) % m], remain[i], remain[(i + 1) % m]]);
+                remain.remove(i);
+            }
+            // 自交或退化的多边形找不到耳朵, 剩余部分退回扇形剖分
+            None => {
+                for i in 1..m - 1 {
+                    tris.push([remain[0], remain[i], remain[i + 1]]);
+                }
+                return tris;
+            }
+        }
+    }
+    tris.push([remain[0], remain[1], remain[2]]);
+    tris
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xy(points: &[[f32; 2]]) -> Vec<Vector3<f32>> {
+        points
+            .iter()
+            .map(|p| Vector3::new(p[0], p[1], 0.0))
+            .collect()
+    }
+
+    // 三角形在xy平面上的有向面积, 逆时针为正
+    fn area(points: &[Vector3<f32>], tri: &[usize; 3]) -> f32 {
+        let (a, b, c) = (points[tri[0]], points[tri[1]], points[tri[2]]);
+        ((b - a).cross(&(c - a))).z / 2.0
+    }
+
+    // 每个顶点恰好用到, 三角形都不反向, 面积之和等于多边形面积, 即三角形互不重叠地铺满多边形
+    fn assert_covers(points: &[Vector3<f32>], tris: &[[usize; 3]], expected: f32) {
+        assert_eq!(tris.len(), points.len() - 2);
+        for i in 0..points.len() {
+            assert!(tris.iter().any(|t| t.contains(&i)), "vertex {} unused", i);
+        }
+        for tri in tris {
+            assert!(area(points, tri) >= 0.0, "{:?} is clockwise", tri);
+        }
+        let total: f32 = tris.iter().map(|t| area(points, t)).sum();
+        assert!((total - expected).abs() < 1e-5, "{} != {}", total, expected);
+    }
+
+    #[test]
+    fn concave_polygon() {
+        // L形, 顶点3为凹角, 从顶点0扇形剖分会越出多边形
+        let points = xy(&[
+            [0.0, 0.0],
+            [2.0, 0.0],
+            [2.0, 1.0],
+            [1.0, 1.0],
+            [1.0, 2.0],
+            [0.0, 2.0],
+        ]);
+        let tris = triangulate(&points);
+        assert_covers(&points, &tris, 3.0);
+        assert_ne!(tris, fan(points.len()));
+
+        // 箭头形, 凹角紧邻顶点0
+        let points = xy(&[[0.0, 0.0], [2.0, 1.0], [0.0, 2.0], [0.5, 1.0]]);
+        assert_covers(&points, &triangulate(&points), 1.5);
+    }
+
+    #[test]
+    fn concave_polygon_off_axis() {
+        // 同一个L形放到yz平面并按顺时针给出, 结果仍应按原顺序逆时针铺满
+        let l = [
+            [0.0, 0.0],
+            [2.0, 0.0],
+            [2.0, 1.0],
+            [1.0, 1.0],
+            [1.0, 2.0],
+            [0.0, 2.0],
+        ];
+        let points: Vec<Vector3<f32>> = l
+            .iter()
+            .rev()
+            .map(|p| Vector3::new(0.0, p[0], p[1]))
+            .collect();
+        let tris = triangulate(&points);
+        assert_eq!(tris.len(), 4);
+        let flat: Vec<Vector3<f32>> = points.iter().map(|p| Vector3::new(p.z, p.y, 0.0)).collect();
+        assert_covers(&flat, &tris, 3.0);
+    }
+
+    #[test]
+    fn collinear_point() {
+        // 凸多边形的一条边上多出一个点
+        let points = xy(&[[0.0, 0.0], [2.0, 0.0], [2.0, 1.0], [2.0, 2.0], [0.0, 2.0]]);
+        assert_covers(&points, &triangulate(&points), 4.0);
+
+        // 凹多边形的边上多出一个点, 共线顶点不能作为耳朵
+        let points = xy(&[
+            [0.0, 0.0],
+            [1.0, 0.0],
+            [2.0, 0.0],
+            [2.0, 1.0],
+            [1.0, 1.0],
+            [1.0, 2.0],
+            [0.0, 2.0],
+        ]);
+        assert_covers(&points, &triangulate(&points), 3.0);
+    }
+
+    #[test]
+    fn degenerate_polygon() {
+        assert!(triangulate(&[]).is_empty());
+        assert!(triangulate(&xy(&[[0.0, 0.0], [1.0, 0.0]])).is_empty());
+        assert_eq!(
+            triangulate(&xy(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]])),
+            vec![[0, 1, 2]]
+        );
+
+        // 所有顶点共线或重合时没有法线, 退回扇形剖分而不是丢弃面片
+        let line = xy(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]]);
+        assert_eq!(triangulate(&line), fan(4));
+        let point = xy(&[[1.0, 1.0], [1.0, 1.0], [1.0, 1.0], [1.0, 1.0], [1.0, 1.0]]);
+        assert_eq!(triangulate(&point), fan(5));
+
+        // 自交的蝴蝶结找不到合法的剖分, 仍输出n-2个三角形
+        let bowtie = xy(&[[0.0, 0.0], [2.0, 2.0], [2.0, 0.0], [0.0, 2.0]]);
+        assert_eq!(triangulate(&bowtie).len(), 2);
+    }
+}
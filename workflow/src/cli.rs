@@ -0,0 +1,377 @@
+use std::fmt::{Display, Error, Formatter};
+
+use na::Vector3;
+
+use delusion::graphics::{MsaaOptions, ToneMap};
+use delusion::light::{Light, LightKind};
+use delusion::post;
+use delusion::post::PostEffect;
+use delusion::scene::{Scene, SceneError, SceneModel};
+use delusion::shader::ShaderKind;
+use objcracker::NormalMode;
+
+/////////////////////////////////////////////////////////////////////////////////
+
+pub static USAGE: &str = "\
+用法: workflow [选项] <模型路径前缀> <模型名>...
+      workflow [选项] --scene <场景文件>
+
+示例: workflow ./obj/ african_head floor
+      workflow --scene ./scene.json --msaa 4
+
+选项:
+    --scene <文件>          从JSON场景文件读取模型, 光源, 相机与渲染设置
+                            命令行中显式给出的选项覆盖场景文件中的对应设置
+    --width <像素>          画布宽度, 默认800
+    --height <像素>         画布高度, 默认800
+    --shader <名称>         所有模型的初始着色器: weird, model, gouraud, diff, nm, spec,
+                            nmspec, tbn, pbr, depth, 默认nmspec
+    --normals <方式>        模型缺少法线时的生成方式: flat, smooth, 默认smooth
+    --msaa <采样数>         多重采样: 0(关闭), 2, 4, 8, 16, 默认0
+    --tonemap <算子>        色调映射: none, reinhard, aces, exposure, 默认none
+    --exposure <倍率>       色调映射前的曝光倍率, 默认1
+    --post <效果,...>       按顺序启用的后处理: fxaa, bloom, vignette, sharpen, ssao, none
+                            替换场景文件中的后处理列表, LUT调色需在场景文件中配置
+    --eye <x,y,z>           相机位置, 默认0,1,3
+    --light <x,y,z>         第一个光源改为指向该方向的平行光, 默认0,1,1
+    --background <r,g,b>    背景色(0~255), 默认255,255,255
+    --headless              不创建窗口, 渲染后写出图像
+    --frames <N>            无窗口模式下渲染的帧数, 默认1
+    --out <模板>            无窗口模式的输出路径(png/tga), \"{}\"替换为帧序号
+                            默认frame_{}.png
+    -h, --help              显示本帮助";
+
+// 需要携带参数值的选项
+static VALUE_OPTIONS: [&str; 14] = [
+    "--scene",
+    "--width",
+    "--height",
+    "--shader",
+    "--normals",
+    "--msaa",
+    "--tonemap",
+    "--exposure",
+    "--post",
+    "--eye",
+    "--light",
+    "--background",
+    "--frames",
+    "--out",
+];
+
+/////////////////////////////////////////////////////////////////////////////////
+
+pub enum CliError {
+    Help,
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidValue {
+        option: String,
+        value: String,
+        reason: &'static str,
+    },
+    RequiresHeadless(String),
+    MissingModel,
+    ModelsWithScene,
+    Scene(SceneError),
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            CliError::Help => write!(f, "{}", USAGE),
+            CliError::UnknownOption(option) => write!(f, "未知选项 {}", option),
+            CliError::MissingValue(option) => write!(f, "{} 缺少参数值", option),
+            CliError::InvalidValue {
+                option,
+                value,
+                reason,
+            } => write!(f, "{} 的参数值 \"{}\" 非法: {}", option, value, reason),
+            CliError::RequiresHeadless(option) => write!(f, "{} 需与--headless同时使用", option),
+            CliError::MissingModel => write!(f, "缺少模型路径前缀或模型名"),
+            CliError::ModelsWithScene => write!(f, "--scene 不能与模型路径参数同时使用"),
+            CliError::Scene(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+pub struct HeadlessArgs {
+    pub frames: usize,
+    pub out: String,
+}
+
+// 未在命令行中给出的选项为None, 取场景文件或默认场景中的值
+pub struct CliOptions {
+    pub scene: Option<String>,
+    pub prefix: String,
+    pub names: Vec<String>,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub shader: Option<ShaderKind>,
+    pub normals: Option<NormalMode>,
+    pub msaa: Option<MsaaOptions>,
+    pub tonemap: Option<ToneMap>,
+    pub exposure: Option<f32>,
+    pub post: Option<Vec<PostEffect>>,
+    pub eye: Option<Vector3<f32>>,
+    pub light: Option<Vector3<f32>>,
+    pub background: Option<Vector3<f32>>,
+    pub headless: Option<HeadlessArgs>,
+}
+
+impl CliOptions {
+    // args不含程序名
+    pub fn parse(args: &[String]) -> Result<CliOptions, CliError> {
+        let mut options = CliOptions {
+            scene: None,
+            prefix: String::new(),
+            names: Vec::new(),
+            width: None,
+            height: None,
+            shader: None,
+            normals: None,
+            msaa: None,
+            tonemap: None,
+            exposure: None,
+            post: None,
+            eye: None,
+            light: None,
+            background: None,
+            headless: None,
+        };
+        let mut headless = false;
+        let mut frames: Option<usize> = None;
+        let mut out: Option<String> = None;
+        let mut positional: Vec<String> = Vec::new();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let option = arg.as_str();
+            if !option.starts_with('-') || option == "-" {
+                positional.push(arg.clone());
+                continue;
+            }
+            if option == "-h" || option == "--help" {
+                return Err(CliError::Help);
+            }
+            if option == "--headless" {
+                headless = true;
+                continue;
+            }
+            if !VALUE_OPTIONS.contains(&option) {
+                return Err(CliError::UnknownOption(arg.clone()));
+            }
+            let value = iter
+                .next()
+                .ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+            let invalid = |reason: &'static str| CliError::InvalidValue {
+                option: arg.clone(),
+                value: value.clone(),
+                reason,
+            };
+            match option {
+                "--scene" => options.scene = Some(value.clone()),
+                "--width" => {
+                    options.width = Some(parse_extent(value).ok_or_else(|| invalid("需为正整数"))?)
+                }
+                "--height" => {
+                    options.height = Some(parse_extent(value).ok_or_else(|| invalid("需为正整数"))?)
+                }
+                "--shader" => {
+                    options.shader = Some(ShaderKind::from_name(value).ok_or_else(|| {
+                        invalid(
+                            "可选 weird, model, gouraud, diff, nm, spec, nmspec, tbn, pbr, depth",
+                        )
+                    })?)
+                }
+                "--normals" => {
+                    options.normals = Some(match value.as_str() {
+                        "flat" => NormalMode::Flat,
+                        "smooth" => NormalMode::Smooth,
+                        _ => return Err(invalid("可选 flat, smooth")),
+                    })
+                }
+                "--msaa" => {
+                    options.msaa = Some(
+                        value
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(MsaaOptions::from_samples)
+                            .ok_or_else(|| invalid("可选 0, 2, 4, 8, 16"))?,
+                    )
+                }
+                "--tonemap" => {
+                    options.tonemap = Some(
+                        ToneMap::from_name(value)
+                            .ok_or_else(|| invalid("可选 none, reinhard, aces, exposure"))?,
+                    )
+                }
+                "--exposure" => {
+                    options.exposure = Some(
+                        value
+                            .parse::<f32>()
+                            .ok()
+                            .filter(|x| x.is_finite() && *x > 0.0)
+                            .ok_or_else(|| invalid("需为正数"))?,
+                    )
+                }
+                "--post" => {
+                    let effects = match value.as_str() {
+                        "none" => Vec::new(),
+                        _ => value
+                            .split(',')
+                            .map(|name| PostEffect::from_name(name.trim()))
+                            .collect::<Option<Vec<PostEffect>>>()
+                            .ok_or_else(|| {
+                                invalid("可选 fxaa, bloom, vignette, sharpen, ssao, none")
+                            })?,
+                    };
+                    if post::misordered(&effects).is_some() {
+                        return Err(invalid("bloom, ssao 在色调映射前执行, 需排在其他效果之前"));
+                    }
+                    options.post = Some(effects);
+                }
+                "--eye" => {
+                    options.eye = Some(parse_vec3(value).ok_or_else(|| invalid("格式为x,y,z"))?)
+                }
+                "--light" => {
+                    let light = parse_vec3(value).ok_or_else(|| invalid("格式为x,y,z"))?;
+                    if light.norm() <= f32::EPSILON {
+                        return Err(invalid("光照方向不能为零向量"));
+                    }
+                    options.light = Some(light);
+                }
+                "--background" => {
+                    options.background = Some(
+                        parse_vec3(value)
+                            .filter(|c| c.iter().all(|x| (0.0..=255.0).contains(x)))
+                            .ok_or_else(|| invalid("格式为r,g,b, 分量取0~255"))?,
+                    )
+                }
+                "--frames" => {
+                    frames = Some(parse_extent(value).ok_or_else(|| invalid("需为正整数"))?)
+                }
+                "--out" => {
+                    let lower = value.to_lowercase();
+                    if !lower.ends_with(".png") && !lower.ends_with(".tga") {
+                        return Err(invalid("仅支持png/tga"));
+                    }
+                    out = Some(value.clone());
+                }
+                _ => return Err(CliError::UnknownOption(arg.clone())),
+            }
+        }
+
+        if !headless && (frames.is_some() || out.is_some()) {
+            let option = match frames.is_some() {
+                true => "--frames",
+                false => "--out",
+            };
+            return Err(CliError::RequiresHeadless(option.to_string()));
+        }
+        if headless {
+            options.headless = Some(HeadlessArgs {
+                frames: frames.unwrap_or(1),
+                out: out.unwrap_or_else(|| String::from("frame_{}.png")),
+            });
+        }
+        if options.scene.is_some() {
+            if !positional.is_empty() {
+                return Err(CliError::ModelsWithScene);
+            }
+            return Ok(options);
+        }
+        if positional.len() < 2 {
+            return Err(CliError::MissingModel);
+        }
+        options.prefix = positional.remove(0);
+        options.names = positional;
+        Ok(options)
+    }
+
+    // 读取场景文件, 或由模型路径参数构造默认场景, 再以显式给出的选项覆盖
+    pub fn scene(&self) -> Result<Scene, CliError> {
+        let mut scene = match &self.scene {
+            Some(file) => Scene::load(file).map_err(CliError::Scene)?,
+            None => {
+                let mut scene = Scene::new();
+                for name in &self.names {
+                    let path = format!("{}{}", self.prefix, name);
+                    scene.models.push(SceneModel::new(&path));
+                }
+                scene
+            }
+        };
+        let settings = &mut scene.settings;
+        settings.width = self.width.unwrap_or(settings.width);
+        settings.height = self.height.unwrap_or(settings.height);
+        settings.msaa = self.msaa.unwrap_or(settings.msaa);
+        settings.background = self.background.unwrap_or(settings.background);
+        settings.tone.operator = self.tonemap.unwrap_or(settings.tone.operator);
+        settings.tone.exposure = self.exposure.unwrap_or(settings.tone.exposure);
+        if let Some(post) = &self.post {
+            settings.post = post.clone();
+        }
+        if let Some(shader) = self.shader {
+            settings.shader = shader;
+            clear_shaders(&mut scene.models);
+        }
+        if let Some(mode) = self.normals {
+            set_normal_mode(&mut scene.models, mode);
+        }
+        if let Some(light) = &self.light {
+            scene.lights[0] = Light {
+                kind: LightKind::Directional {
+                    direction: light.normalize(),
+                },
+                ..scene.lights[0]
+            };
+        }
+        if let Some(eye) = self.eye {
+            scene.camera.eye = eye;
+            if !scene.camera.is_valid() {
+                return Err(CliError::InvalidValue {
+                    option: String::from("--eye"),
+                    value: format!("{},{},{}", eye.x, eye.y, eye.z),
+                    reason: "视线不能与相机上方向平行",
+                });
+            }
+        }
+        Ok(scene)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 命令行指定着色器时忽略场景中各模型自己的着色器
+fn clear_shaders(models: &mut [SceneModel]) {
+    for model in models {
+        model.shader = None;
+        clear_shaders(&mut model.children);
+    }
+}
+
+fn set_normal_mode(models: &mut [SceneModel], mode: NormalMode) {
+    for model in models {
+        model.normals.mode = mode;
+        set_normal_mode(&mut model.children, mode);
+    }
+}
+
+fn parse_extent(value: &str) -> Option<usize> {
+    value.parse::<usize>().ok().filter(|&n| n > 0)
+}
+
+fn parse_vec3(value: &str) -> Option<Vector3<f32>> {
+    let c: Vec<f32> = value
+        .split(',')
+        .map(|x| x.trim().parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()
+        .ok()?;
+    match c.len() == 3 && c.iter().all(|x| x.is_finite()) {
+        true => Some(Vector3::new(c[0], c[1], c[2])),
+        false => None,
+    }
+}
@@ -1,84 +1,121 @@
-extern crate image;
+extern crate delusion;
 extern crate minifb;
 extern crate nalgebra as na;
 extern crate objcracker;
 
-use std::{env, time::SystemTime};
+use std::{env, process, time::SystemTime};
 
 use minifb::{Key, Window, WindowOptions};
-use na::{Matrix4, Vector3, Vector4};
+use na::{Unit, UnitQuaternion, Vector3};
 
-use crate::graphics::MsaaOptions;
-use crate::objcracker::Objcracker;
-use crate::shader::*;
-use crate::transform::*;
+use delusion::graphics;
+use delusion::graphics::{CullMode, MsaaOptions, TileOptions, ToneMap, ToneOptions};
+use delusion::headless;
+use delusion::headless::HeadlessOptions;
+use delusion::light::{Light, LightKind};
+use delusion::scene::SceneInstance;
+use delusion::shader::{ShaderKind, ShaderPayload};
+use delusion::Delusion;
+use objcracker::{Filter, Sampler};
 
-mod delusion;
-mod graphics;
-mod primitives;
-mod shader;
-mod transform;
+use crate::cli::{CliError, CliOptions};
+
+mod cli;
 
 /////////////////////////////////////////////////////////////////////////////////
 
 static TITLE: &str = "Delusion Canvas";
 
-static WIDTH: usize = 800;
-static HEIGHT: usize = 800;
-
-static UP: Vector3<f32> = Vector3::new(0.0, 1.0, 0.0);
-static ORIGIN: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
-
-static WHITE_COLOR: Vector3<f32> = Vector3::new(255.0, 255.0, 255.0);
 static CLEAR_COLOR: Vector3<f32> = Vector3::new(5.0, 5.0, 5.0);
 static CLEAR_COLOR_2: Vector3<f32> = Vector3::new(255.0, 255.0, 255.0);
 
 static AXIS_X: Vector3<f32> = Vector3::new(1.0, 0.0, 0.0);
 static AXIS_Y: Vector3<f32> = Vector3::new(0.0, 1.0, 0.0);
-static AXIS_Z: Vector3<f32> = Vector3::new(0.0, 0.0, 1.0);
 
 /////////////////////////////////////////////////////////////////////////////////
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match CliOptions::parse(&args) {
+        Ok(options) => options,
+        Err(CliError::Help) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("Delusion::Error::{}", e);
+            eprintln!("使用 --help 查看用法");
+            process::exit(2);
+        }
+    };
+    let scene = match options.scene() {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("Delusion::Error::{}", e);
+            if let CliError::InvalidValue { .. } = e {
+                eprintln!("使用 --help 查看用法");
+            }
+            process::exit(2);
+        }
+    };
+    let (width, height) = (scene.settings.width, scene.settings.height);
 
     /////////////////////////////////////////////////////////////////////////////////
 
-    let mut models: Vec<Objcracker> = Vec::new();
-    for i in 2..args.len() {
-        let mut model = Objcracker::new(&format!("{}{}", args[1], args[i]));
-        model.interpret().unwrap();
-        models.push(model);
+    // 加载失败的模型跳过, 其所在节点仍参与子节点的变换
+    let (mut instance, errors) = scene.instantiate();
+    for e in errors {
+        eprintln!("Delusion::Error::{}", e);
     }
 
     /////////////////////////////////////////////////////////////////////////////////
 
-    let mut light: Vector3<f32> = Vector3::new(0.0, 1.0, 1.0).normalize();
-    let mut eye: Vector3<f32> = Vector3::new(0.0, 1.0, 3.0);
-    let mut clear_color: Vector3<f32> = WHITE_COLOR;
+    let mut lights: Vec<Light> = scene.lights.clone();
+    let mut camera = scene.camera;
+    let mut clear_color: Vector3<f32> = scene.settings.background;
+
+    /////////////////////////////////////////////////////////////////////////////////
 
-    let mut m_model: Matrix4<f32> = Matrix4::<f32>::identity();
+    let mut d = scene.renderer();
+    let (chain, errors) = scene.post_chain();
+    for e in errors {
+        eprintln!("Delusion::Error::{}", e);
+    }
+    d.set_post_chain(chain);
 
     /////////////////////////////////////////////////////////////////////////////////
 
-    let mut d = delusion::Delusion::new(WIDTH, HEIGHT);
-    d.set_camera(graphics::calc_m_camera(&eye, &ORIGIN, &UP));
-    d.set_viewport(graphics::calc_m_viewport(WIDTH, HEIGHT, 0.75));
-    d.set_projection(graphics::calc_m_projection(-1.0 / (eye - ORIGIN).norm()));
+    let mut shaders: Vec<Box<dyn ShaderPayload>> =
+        instance.shaders.iter().map(ShaderKind::build).collect();
 
     /////////////////////////////////////////////////////////////////////////////////
 
-    let m = d.projection() * d.camera();
-    let mit = m.try_inverse().unwrap().transpose();
-    let mut shader: Box<dyn ShaderPayload> = Box::new(shader::PhongShaderNmSpec::new(&m, &mit));
-    //let mut shader = shader::PhongShaderNmSpec::new(&m,&mit);
-    //let mut shader = shader::PhongShaderModel::new();
-    //let mut shader = shader::PhongShaderSpec::new(&m);
-    //let mut shader = shader::PhongShaderDiff::new();
+    if let Some(args) = &options.headless {
+        let items = instance.graph.draw_items(&instance.models);
+        let options = HeadlessOptions::new(args.frames, &args.out, &clear_color);
+        match headless::render_headless(&mut d, &items, &mut shaders, &lights, &options) {
+            Ok(paths) => {
+                for path in paths {
+                    println!("Delusion::Headless::{}", path);
+                }
+            }
+            Err(e) => {
+                eprintln!("Delusion::Error::{}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
 
     /////////////////////////////////////////////////////////////////////////////////
 
-    let mut window = Window::new(TITLE, WIDTH, HEIGHT, WindowOptions::default()).unwrap();
+    let mut window = match Window::new(TITLE, width, height, WindowOptions::default()) {
+        Ok(window) => window,
+        Err(e) => {
+            eprintln!("Delusion::Error::{}", e);
+            process::exit(1);
+        }
+    };
     while window.is_open() && !window.is_key_down(Key::Escape) {
         let frame_start_time = SystemTime::now();
 
@@ -86,19 +123,14 @@ fn main() {
 
         d.clear_frame_buff(&clear_color);
         d.clear_depth_buff();
-        d.set_model(m_model);
-        d.set_camera(graphics::calc_m_camera(&eye, &ORIGIN, &UP));
-
-        for model in &models {
-            for i in 0..model.nfaces() {
-                let mut screen_coords: Vector3<Vector4<f32>> = Default::default();
-                for j in 0..3 {
-                    screen_coords[j] = shader.vertex(i, j, &light, &model, &d);
-                }
-                //println!("{:?}", screen_coords[i]);
-                d.rasterize_tri(&screen_coords, &mut shader, &model);
-            }
-        }
+        d.set_camera(camera.m_camera());
+        d.set_eye(&camera.eye);
+        d.set_projection(camera.m_projection());
+
+        instance.graph.update_world();
+        let items = instance.graph.draw_items(&instance.models);
+        d.draw_items(&items, &mut shaders, &lights);
+        d.resolve();
 
         /////////////////////////////////////////////////////////////////////////////////
 
@@ -112,67 +144,75 @@ fn main() {
                 match t {
                     Key::Key1 => {
                         println!("1 Pressed");
-                        shader = Box::new(WeirdShader::new());
+                        set_shaders(&mut shaders, ShaderKind::Weird);
                     }
                     Key::Key2 => {
                         println!("2 Pressed");
-                        shader = Box::new(PhongShaderModel::new());
+                        set_shaders(&mut shaders, ShaderKind::Model);
                     }
                     Key::Key3 => {
                         println!("3 Pressed");
-                        shader = Box::new(GouraudShader::new());
+                        set_shaders(&mut shaders, ShaderKind::Gouraud);
                     }
                     Key::Key4 => {
-                        shader = Box::new(PhongShaderDiff::new());
+                        set_shaders(&mut shaders, ShaderKind::Diff);
                         println!("4 Pressed");
                     }
                     Key::Key5 => {
-                        shader = Box::new(PhongShaderNm::new(&m, &mit));
+                        set_shaders(&mut shaders, ShaderKind::Nm);
                         println!("5 Pressed");
                     }
                     Key::Key6 => {
-                        shader = Box::new(PhongShaderSpec::new(&m));
+                        set_shaders(&mut shaders, ShaderKind::Spec);
                         println!("6 Pressed");
                     }
                     Key::Key7 => {
-                        shader = Box::new(PhongShaderNmSpec::new(&m, &mit));
+                        set_shaders(&mut shaders, ShaderKind::NmSpec);
                         println!("7 Pressed");
                     }
                     Key::Key8 => {
-                        shader = Box::new(DepthShader::new());
+                        set_shaders(&mut shaders, ShaderKind::Depth);
                         println!("8 Pressed");
                     }
+                    Key::Key9 => {
+                        set_shaders(&mut shaders, ShaderKind::Tbn);
+                        println!("9 Pressed");
+                    }
+                    Key::Key0 => {
+                        set_shaders(&mut shaders, ShaderKind::Pbr);
+                        println!("0 Pressed");
+                    }
                     Key::Left => {
                         println!("Left Pressed");
-                        eye.x = eye.x - 0.8;
+                        camera.eye.x -= 0.8;
                     }
                     Key::Right => {
                         println!("Right Pressed");
-                        eye.x = eye.x + 0.8;
+                        camera.eye.x += 0.8;
                     }
                     Key::Up => {
                         println!("Up Pressed");
-                        eye.y = eye.y + 0.8;
+                        camera.eye.y += 0.8;
                     }
                     Key::Down => {
                         println!("Down Pressed");
-                        eye.y = eye.y - 0.8;
+                        camera.eye.y -= 0.8;
                     }
                     Key::A => {
                         println!("W Pressed");
-                        light = (graphics::calc_m_model(AXIS_Y, -20.0, 1.0) * vec3f_to_vec4f(&light, 1.0)).xyz();
+                        rotate_lights(&mut lights, -20.0);
                     }
                     Key::D => {
                         println!("Right Pressed");
-                        light = (graphics::calc_m_model(AXIS_Y, 20.0, 1.0) * vec3f_to_vec4f(&light, 1.0)).xyz();
+                        rotate_lights(&mut lights, 20.0);
                     }
                     Key::W => {
                         println!("Up Pressed");
-                        light.y = light.y + 0.5;
+                        raise_lights(&mut lights, 0.5);
                     }
                     Key::S => {
                         println!("Down Pressed");
-                        light.y = light.y - 0.5;
+                        raise_lights(&mut lights, -0.5);
                     }
                     Key::Q => {
                         println!("Q Pressed");
@@ -187,45 +227,192 @@ fn main() {
                     }
                     Key::N => {
                         println!("N Pressed");
-                        d.enable_msaa(MsaaOptions::X4);
+                        let option = match d.msaa_status() {
+                            MsaaOptions::Disable | MsaaOptions::X16 => MsaaOptions::X2,
+                            MsaaOptions::X2 => MsaaOptions::X4,
+                            MsaaOptions::X4 => MsaaOptions::X8,
+                            MsaaOptions::X8 => MsaaOptions::X16,
+                        };
+                        d.enable_msaa(option);
+                    }
+                    Key::C => {
+                        println!("C Pressed");
+                        let mode = match d.cull_mode() {
+                            CullMode::None => CullMode::Back,
+                            CullMode::Back => CullMode::Front,
+                            CullMode::Front => CullMode::None,
+                        };
+                        d.set_cull_mode(mode, d.front_face());
+                    }
+                    Key::T => {
+                        println!("T Pressed");
+                        match d.tile_status() {
+                            Some(_) => d.disable_tiling(),
+                            None => d.enable_tiling(TileOptions::auto()),
+                        };
+                    }
+                    Key::F => {
+                        println!("F Pressed");
+                        let filter = match d.sampler().filter() {
+                            Filter::Nearest => Filter::Bilinear,
+                            Filter::Bilinear => Filter::Trilinear,
+                            Filter::Trilinear => Filter::Nearest,
+                        };
+                        d.set_sampler(Sampler::new(filter));
+                    }
+                    Key::H => {
+                        println!("H Pressed");
+                        match d.shadow_status() {
+                            Some(_) => d.disable_shadow(),
+                            None => d.enable_shadow(scene.settings.shadow.unwrap_or_default()),
+                        }
+                    }
+                    Key::O => {
+                        println!("O Pressed");
+                        let tone = *d.tone_mapping();
+                        let operator = match tone.operator {
+                            ToneMap::None => ToneMap::Reinhard,
+                            ToneMap::Reinhard => ToneMap::Aces,
+                            ToneMap::Aces => ToneMap::Exposure,
+                            ToneMap::Exposure => ToneMap::None,
+                        };
+                        d.set_tone_mapping(ToneOptions::new(operator, tone.exposure));
+                    }
+                    Key::LeftBracket => {
+                        println!("LeftBracket Pressed");
+                        let tone = *d.tone_mapping();
+                        d.set_tone_mapping(ToneOptions::new(tone.operator, tone.exposure * 0.8));
+                    }
+                    Key::RightBracket => {
+                        println!("RightBracket Pressed");
+                        let tone = *d.tone_mapping();
+                        d.set_tone_mapping(ToneOptions::new(tone.operator, tone.exposure * 1.25));
+                    }
+                    Key::F1 => {
+                        println!("F1 Pressed");
+                        toggle_post(&mut d, "fxaa");
+                    }
+                    Key::F2 => {
+                        println!("F2 Pressed");
+                        toggle_post(&mut d, "bloom");
+                    }
+                    Key::F3 => {
+                        println!("F3 Pressed");
+                        toggle_post(&mut d, "vignette");
+                    }
+                    Key::F4 => {
+                        println!("F4 Pressed");
+                        toggle_post(&mut d, "sharpen");
+                    }
+                    Key::F5 => {
+                        println!("F5 Pressed");
+                        toggle_post(&mut d, "lut");
+                    }
+                    Key::F6 => {
+                        println!("F6 Pressed");
+                        toggle_post(&mut d, "ssao");
                     }
                     Key::I => {
                         println!("I Pressed");
-                        m_model = graphics::calc_m_model(AXIS_X, -20.0, 1.0) * d.model();
+                        rotate_root(&mut instance, AXIS_X, -20.0);
                     }
                     Key::J => {
                         println!("J Pressed");
-                        m_model = graphics::calc_m_model(AXIS_Y, -20.0, 1.0) * d.model();
+                        rotate_root(&mut instance, AXIS_Y, -20.0);
                     }
                     Key::K => {
                         println!("K Pressed");
-                        m_model = graphics::calc_m_model(AXIS_X, 20.0, 1.0) * d.model();
+                        rotate_root(&mut instance, AXIS_X, 20.0);
                     }
                     Key::L => {
                         println!("L Pressed");
-                        m_model = graphics::calc_m_model(AXIS_Y, 20.0, 1.0) * d.model();
+                        rotate_root(&mut instance, AXIS_Y, 20.0);
                     }
                     Key::Minus => {
                         println!("Minus Pressed");
-                        m_model = graphics::calc_m_model(AXIS_Y, 0.0, 0.8) * d.model();
+                        scale_root(&mut instance, 0.8);
                     }
                     Key::Equal => {
                         println!("Equal Pressed");
-                        m_model = graphics::calc_m_model(AXIS_Y, 0.0, 1.2) * d.model();
+                        scale_root(&mut instance, 1.2);
                     }
                     _ => (),
                 }
             }
         });
         window
-            .update_with_buffer(d.get_frame_buff(), WIDTH, HEIGHT)
+            .update_with_buffer(d.get_frame_buff(), width, height)
             .unwrap();
         window.set_title(&format!(
-            "{}MSAA  {} - 帧时间:{}ms/{}fps",
+            "{}MSAA  Cull:{}  {}  Shadow:{}  Tone:{}  Post:{}  {} - 帧时间:{}ms/{}fps",
             d.msaa_status(),
+            d.cull_mode(),
+            d.sampler(),
+            match d.shadow_status() {
+                Some(_) => "On",
+                None => "Off",
+            },
+            d.tone_mapping(),
+            d.post_chain(),
             TITLE,
             frame_time,
             1000 / frame_time
         ));
     }
 }
+
+/////////////////////////////////////////////////////////////////////////////////
+
+// 交互旋转缩放作用于场景图的根节点, 即整个场景
+fn rotate_root(instance: &mut SceneInstance, axis: Vector3<f32>, angle: f32) {
+    let mut root = *instance.graph.local(instance.root);
+    let rotation = UnitQuaternion::from_axis_angle(
+        &Unit::new_normalize(axis),
+        graphics::degree_to_radian(angle),
+    );
+    root.rotation = rotation * root.rotation;
+    instance.graph.set_local(instance.root, root);
+}
+
+// 所有光源绕y轴旋转
+fn rotate_lights(lights: &mut [Light], angle: f32) {
+    let m = graphics::calc_m_model(AXIS_Y, angle, 1.0);
+    for light in lights.iter_mut() {
+        *light = light.transformed(&m);
+    }
+}
+
+// 平行光的方向与点光源, 聚光灯的位置上下移动
+fn raise_lights(lights: &mut [Light], dy: f32) {
+    for light in lights.iter_mut() {
+        match &mut light.kind {
+            LightKind::Directional { direction } => {
+                direction.y += dy;
+                *direction = direction.normalize();
+            }
+            LightKind::Point { position, .. } | LightKind::Spot { position, .. } => {
+                position.y += dy
+            }
+        }
+    }
+}
+
+fn scale_root(instance: &mut SceneInstance, factor: f32) {
+    let mut root = *instance.graph.local(instance.root);
+    root.scale *= factor;
+    instance.graph.set_local(instance.root, root);
+}
+
+// F1~F6开关后处理, 场景未配置LUT时调色不可用
+fn toggle_post(d: &mut Delusion, name: &str) {
+    if d.post_chain_mut().toggle(name).is_none() {
+        println!("Delusion::Debug::未配置后处理{}。", name);
+    }
+}
+
+// 数字键切换着色器时替换所有模型的着色器
+fn set_shaders(shaders: &mut [Box<dyn ShaderPayload>], kind: ShaderKind) {
+    for shader in shaders.iter_mut() {
+        *shader = kind.build();
+    }
+}